@@ -1,11 +1,19 @@
 use crate::io;
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::widgets::TableState;
+use std::sync::Arc;
 
 use crate::{
-    db::{self, DB},
-    typingtest::TypingTest,
+    auth,
+    code::{Language, SnippetBook},
+    custom::CustomText,
+    db::{self, PassphraseCheck, DB},
+    history::{self, DateRange, HistoryFilter, HistoryPage, SortColumn, UserScope, PAGE_SIZE},
+    metrics::Metrics,
+    quotes::{QuoteBook, QuoteLength},
+    typingtest::{TestMode, TypingTest},
     ui,
+    words::{WordBand, WordList, WordSource},
 };
 
 pub type AppResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
@@ -22,6 +30,53 @@ pub enum Screen {
     Pause,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuItem {
+    QuickTest,
+    CustomText,
+    Login,
+    SwitchUser,
+    Passphrase,
+    LogOut,
+    History,
+    Quit,
+    TestOpts,
+}
+
+impl MenuItem {
+    pub fn label(&self) -> &'static str {
+        match self {
+            MenuItem::QuickTest => "Quick Test",
+            MenuItem::CustomText => "Custom Text",
+            MenuItem::Login => "Login",
+            MenuItem::SwitchUser => "Switch User",
+            MenuItem::Passphrase => "Set Passphrase",
+            MenuItem::LogOut => "Log Out",
+            MenuItem::History => "Test History",
+            MenuItem::Quit => "Quit",
+            MenuItem::TestOpts => "TestOpts",
+        }
+    }
+}
+
+/// Where the login screen is up to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LoginStep {
+    Username,
+    /// The named user has a passphrase, it's typed into `login_input`.
+    Passphrase {
+        user_id: i64,
+        username: String,
+    },
+    /// The logged in user proving it's them before changing their passphrase.
+    CurrentPassphrase,
+    /// Choosing a passphrase for the logged in user, leaving it empty removes it.
+    NewPassphrase,
+    ConfirmPassphrase {
+        first: String,
+    },
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Clone, Copy)]
 enum InputMode {
@@ -35,14 +90,67 @@ pub struct TestOpts {
     pub word_input: String,
     pub seconds_options: Vec<u16>,
     pub seconds_selected: usize,
+    pub options_selected: usize,
+    pub word_lists: WordSource,
+    pub word_list_selected: usize,
+    pub band: WordBand,
+    pub punctuation: bool,
+    pub numbers: bool,
+    pub seed_input: String,
+    /// How many lines of the test are on screen at once.
+    pub visible_lines: usize,
+    // what the status bar above the test shows
+    pub show_timer: bool,
+    pub show_progress: bool,
+    pub show_wpm: bool,
+    pub show_accuracy: bool,
+    pub quotes: QuoteBook,
+    pub quote_selected: usize,
+    pub snippets: SnippetBook,
+    pub code_selected: usize,
 }
 
 #[derive(Clone, Debug)]
 pub enum TestOptsFocus {
     Words,
     Seconds,
+    Quote,
+    Code,
+    Options,
+}
+
+/// Rows of the options list on the TestOpts screen, changed with Left/Right.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TestOption {
+    WordList,
+    Band,
+    Punctuation,
+    Numbers,
+    Seed,
+    Lines,
+    Timer,
+    Progress,
+    LiveWpm,
+    LiveAccuracy,
+}
+
+impl TestOption {
+    pub const ALL: [TestOption; 10] = [
+        TestOption::WordList,
+        TestOption::Band,
+        TestOption::Punctuation,
+        TestOption::Numbers,
+        TestOption::Seed,
+        TestOption::Lines,
+        TestOption::Timer,
+        TestOption::Progress,
+        TestOption::LiveWpm,
+        TestOption::LiveAccuracy,
+    ];
 }
 
+const VISIBLE_LINE_CHOICES: [usize; 4] = [1, 3, 5, 7];
+
 impl Default for TestOpts {
     fn default() -> Self {
         Self {
@@ -50,15 +158,133 @@ impl Default for TestOpts {
             word_input: String::new(),
             seconds_options: vec![15, 30, 60],
             seconds_selected: 0,
+            options_selected: 0,
+            word_lists: WordSource::default(),
+            word_list_selected: 0,
+            band: WordBand::Full,
+            punctuation: false,
+            numbers: false,
+            seed_input: String::new(),
+            visible_lines: 3,
+            show_timer: true,
+            show_progress: true,
+            show_wpm: true,
+            show_accuracy: true,
+            quotes: QuoteBook::default(),
+            quote_selected: 0,
+            snippets: SnippetBook::default(),
+            code_selected: 0,
         }
     }
 }
 impl TestOpts {
     fn new() -> Self {
         Self {
+            word_lists: WordSource::load(),
+            quotes: QuoteBook::load(),
+            snippets: SnippetBook::load(&[]),
             ..Default::default()
         }
     }
+
+    pub fn selected_word_list(&self) -> &Arc<WordList> {
+        self.word_lists.get(self.word_list_selected)
+    }
+
+    pub fn option_label(&self, option: TestOption) -> String {
+        match option {
+            TestOption::WordList => format!("Word list: {}", self.selected_word_list().name),
+            TestOption::Band => format!("Difficulty: {}", self.band.as_str()),
+            TestOption::Punctuation => format!("Punctuation: {}", on_off(self.punctuation)),
+            TestOption::Numbers => format!("Numbers: {}", on_off(self.numbers)),
+            TestOption::Seed if self.seed_input.is_empty() => "Seed: random".to_string(),
+            TestOption::Seed => format!("Seed: {}", self.seed_input),
+            TestOption::Lines => format!("Lines shown: {}", self.visible_lines),
+            TestOption::Timer => format!("Countdown: {}", on_off(self.show_timer)),
+            TestOption::Progress => format!("Words done: {}", on_off(self.show_progress)),
+            TestOption::LiveWpm => format!("Live WPM: {}", on_off(self.show_wpm)),
+            TestOption::LiveAccuracy => format!("Live accuracy: {}", on_off(self.show_accuracy)),
+        }
+    }
+
+    fn cycle_option(&mut self, option: TestOption, forward: bool) {
+        match option {
+            TestOption::WordList => {
+                self.word_list_selected =
+                    cycle(self.word_list_selected, self.word_lists.len(), forward)
+            }
+            TestOption::Band => {
+                let i = WordBand::ALL
+                    .iter()
+                    .position(|b| *b == self.band)
+                    .unwrap_or(0);
+                self.band = WordBand::ALL[cycle(i, WordBand::ALL.len(), forward)];
+            }
+            TestOption::Punctuation => self.punctuation = !self.punctuation,
+            TestOption::Numbers => self.numbers = !self.numbers,
+            TestOption::Timer => self.show_timer = !self.show_timer,
+            TestOption::Progress => self.show_progress = !self.show_progress,
+            TestOption::LiveWpm => self.show_wpm = !self.show_wpm,
+            TestOption::LiveAccuracy => self.show_accuracy = !self.show_accuracy,
+            TestOption::Lines => {
+                let i = VISIBLE_LINE_CHOICES
+                    .iter()
+                    .position(|n| *n == self.visible_lines)
+                    .unwrap_or(0);
+                self.visible_lines =
+                    VISIBLE_LINE_CHOICES[cycle(i, VISIBLE_LINE_CHOICES.len(), forward)];
+            }
+            // typed in directly rather than cycled
+            TestOption::Seed => {}
+        }
+    }
+
+    /// The seed typed on the options panel, if any.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed_input.parse().ok()
+    }
+}
+
+fn on_off(enabled: bool) -> &'static str {
+    if enabled {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+/// What to show after a passphrase didn't match.
+fn passphrase_message(check: PassphraseCheck, username: &str) -> String {
+    match check {
+        PassphraseCheck::Wrong { tries_left } => format!(
+            "Wrong passphrase, {} {} left",
+            tries_left,
+            if tries_left == 1 { "try" } else { "tries" }
+        ),
+        PassphraseCheck::Locked { secs_left } => {
+            format!("{} is locked for {} min", username, (secs_left + 59) / 60)
+        }
+        PassphraseCheck::Ok => String::new(),
+    }
+}
+
+/// The entry after `current` in `options`, wrapping around, the first when it isn't there.
+fn next_of<T: Clone + PartialEq>(options: &[T], current: &T) -> Option<T> {
+    let next = options
+        .iter()
+        .position(|option| option == current)
+        .map_or(0, |i| cycle(i, options.len(), true));
+    options.get(next).cloned()
+}
+
+fn cycle(i: usize, len: usize, forward: bool) -> usize {
+    if forward {
+        (i + 1) % len
+    } else if i == 0 {
+        len - 1
+    } else {
+        i - 1
+    }
 }
 
 #[allow(dead_code)]
@@ -71,21 +297,35 @@ pub struct TypeTui {
     character_index: usize,
     pub test_opts: TestOpts,
     pub db: db::DB,
-    pub user: String,
+    pub user: Option<db::User>,
     pub login_input: String,
+    pub login_step: LoginStep,
+    /// Shown under the login prompt, e.g. after a wrong passphrase.
+    pub login_message: Option<String>,
+    /// Results typed without logging in this session, by their `guest_tests` id.
+    guest_tests: Vec<(i64, TypingTest)>,
+    /// A guest result to claim once someone logs in.
+    claim_after_login: Option<i64>,
     pub stats_list_state: ratatui::widgets::TableState,
-    pub history: Vec<(String, i32, i32, i32, i32, i32)>,
+    /// The page of History on screen.
+    pub history: Vec<db::TestRow>,
+    /// Position of the page's first row among all matching rows.
+    pub history_start: usize,
+    pub history_total: usize,
+    pub history_filter: HistoryFilter,
     pub pause_selected: usize,
-}
-
-impl Default for TypeTui {
-    fn default() -> Self {
-        Self::new()
-    }
+    /// Best WPM the user has on the quote they just typed.
+    pub quote_best: Option<i32>,
+    /// Best WPM on the same kind of test before the one just finished.
+    pub previous_best: Option<i32>,
+    pub new_best: bool,
+    /// Text passed on the command line, if any, and the section being practiced.
+    pub custom: Option<CustomText>,
+    pub custom_section: usize,
 }
 
 impl TypeTui {
-    pub fn new() -> TypeTui {
+    pub fn new(db: DB) -> TypeTui {
         let mut state = TableState::default();
         state.select(Some(0));
         TypeTui {
@@ -95,18 +335,59 @@ impl TypeTui {
             input_mode: InputMode::Normal,
             character_index: 0,
             test_opts: TestOpts::new(),
-            db: Result::expect(DB::new(), "error creating db"),
-            user: "".to_string(),
+            // a passphrase has to be typed again on every launch
+            user: db
+                .last_user()
+                .unwrap_or_default()
+                .filter(|user| !user.has_passphrase),
+            db,
             login_input: String::new(),
+            login_step: LoginStep::Username,
+            login_message: None,
+            guest_tests: Vec::new(),
+            claim_after_login: None,
             stats_list_state: state,
             history: Vec::new(),
+            history_start: 0,
+            history_total: 0,
+            history_filter: HistoryFilter::default(),
             pause_selected: 0,
+            quote_best: None,
+            previous_best: None,
+            new_best: false,
+            custom: None,
+            custom_section: 0,
         }
     }
+    fn choose_seed(&mut self) {
+        // generated seeds are kept short so they're easy to read out and share
+        self.typing.seed = self
+            .test_opts
+            .seed()
+            .unwrap_or_else(|| rand::random::<u32>() as u64);
+    }
+
     pub fn load_random_words(&mut self, num_words: usize) {
+        self.typing.word_list = self.test_opts.selected_word_list().clone();
+        self.typing.band = self.test_opts.band;
+        self.typing.punctuation = self.test_opts.punctuation;
+        self.typing.numbers = self.test_opts.numbers;
+        self.choose_seed();
         self.typing.get_words(num_words);
     }
 
+    /// Sets up a quote test, returns false when there is no quote of that length.
+    pub fn load_quote(&mut self, length: QuoteLength) -> bool {
+        self.choose_seed();
+        self.typing.get_quote(&self.test_opts.quotes, length)
+    }
+
+    /// Sets up a code test, returns false when there is no snippet in that language.
+    pub fn load_snippet(&mut self, language: Language) -> bool {
+        self.choose_seed();
+        self.typing.get_snippet(&self.test_opts.snippets, language)
+    }
+
     pub async fn run_app<B: ratatui::prelude::Backend>(
         terminal: &mut ratatui::Terminal<B>,
         app: &mut TypeTui,
@@ -138,17 +419,27 @@ impl TypeTui {
                                 TypingTest::handle_typing_input(key_event.code, app);
                             }
                             crate::app::Screen::History => match key_event.code {
-                                KeyCode::Up => {
-                                    let i = app.stats_list_state.selected().unwrap_or(0);
-                                    let len = app.history.len();
-                                    let new = if i == 0 { len.saturating_sub(1) } else { i - 1 };
-                                    app.stats_list_state.select(Some(new));
+                                KeyCode::Up
+                                | KeyCode::Down
+                                | KeyCode::PageUp
+                                | KeyCode::PageDown
+                                | KeyCode::Home
+                                | KeyCode::End => app.scroll_history(key_event.code),
+                                KeyCode::Char('c') => {
+                                    let selected = app.stats_list_state.selected().unwrap_or(0);
+                                    if let Some(guest_id) =
+                                        app.history.get(selected).and_then(|row| row.guest_id)
+                                    {
+                                        if app.user.is_some() {
+                                            app.claim_guest_test(guest_id);
+                                        } else {
+                                            app.claim_after_login = Some(guest_id);
+                                            app.open_login(LoginStep::Username);
+                                        }
+                                    }
                                 }
-                                KeyCode::Down => {
-                                    let i = app.stats_list_state.selected().unwrap_or(0);
-                                    let len = app.history.len();
-                                    let new = if i + 1 >= len { 0 } else { i + 1 };
-                                    app.stats_list_state.select(Some(new));
+                                KeyCode::Char(c @ ('u' | 'm' | 'l' | 'd' | 's' | 'r')) => {
+                                    app.change_history_filter(c)
                                 }
                                 KeyCode::Esc => {
                                     app.current_screen = Screen::Main { selected_option: 0 }
@@ -162,6 +453,13 @@ impl TypeTui {
                                 KeyCode::Char('q') => {
                                     return Ok(false);
                                 }
+                                KeyCode::Enter if app.typing.mode == TestMode::Custom => {
+                                    if let Some(custom) = &app.custom {
+                                        app.custom_section =
+                                            (app.custom_section + 1) % custom.len();
+                                    }
+                                    app.start_custom_section();
+                                }
                                 KeyCode::Esc => {
                                     app.current_screen = Screen::Main { selected_option: 0 }
                                 }
@@ -186,6 +484,7 @@ impl TypeTui {
                                     app.confirm_login();
                                 }
                                 KeyCode::Esc => {
+                                    app.claim_after_login = None;
                                     app.current_screen = Screen::Main { selected_option: 0 };
                                 }
                                 _ => {}
@@ -203,9 +502,8 @@ impl TypeTui {
                                 }
                                 KeyCode::Enter => match app.pause_selected {
                                     0 => {
-                                        app.typing.user_input = "".to_string();
-                                        app.typing.time = None;
-                                        app.typing.start_time = None;
+                                        app.typing.clear_input();
+                                        app.typing.reset_clock();
                                         app.current_screen = Screen::Typing;
                                     }
                                     1 => {
@@ -220,14 +518,19 @@ impl TypeTui {
                                     _ => {}
                                 },
                                 KeyCode::Esc => {
-                                    // resume
+                                    app.typing.resume();
                                     app.current_screen = Screen::Typing;
                                 }
                                 _ => {}
                             },
                         }
                     }
-                    crate::event::AppEvent::Tick => {}
+                    // the redraw at the top of the loop keeps the status bar's clock moving
+                    crate::event::AppEvent::Tick => {
+                        if let Screen::Typing = app.current_screen {
+                            TypingTest::check_time_limit(app);
+                        }
+                    }
                 }
             }
 
@@ -237,44 +540,220 @@ impl TypeTui {
         }
     }
     pub fn reset_test(&mut self) {
-        self.typing.user_input.clear();
+        self.typing.clear_input();
+        self.typing.metrics = Metrics::default();
         self.typing.correct_char = 0;
         self.typing.wpm = 0;
-        self.typing.start_time = None;
-        self.typing.time = None;
+        self.typing.reset_clock();
         self.typing.time_limit = None;
         self.typing.word_count = 0;
         self.typing.test_text.clear();
+        self.typing.quote = None;
+        self.typing.snippet = None;
+        self.quote_best = None;
+        self.previous_best = None;
+        self.new_best = false;
+    }
+
+    /// Shows the login screen at `step` with an empty input.
+    pub fn open_login(&mut self, step: LoginStep) {
+        self.login_step = step;
+        self.login_input.clear();
+        self.login_message = None;
+        self.current_screen = Screen::Login;
     }
 
     pub fn confirm_login(&mut self) {
-        let uname = self.login_input.trim().to_string();
-        if uname.is_empty() {
-            return;
+        let input = std::mem::take(&mut self.login_input);
+        self.login_message = None;
+        match self.login_step.clone() {
+            LoginStep::Username => {
+                let uname = input.trim();
+                if uname.is_empty() {
+                    return;
+                }
+                match self.db.user_by_name(uname) {
+                    Ok(Some(user)) if user.has_passphrase => {
+                        self.login_step = LoginStep::Passphrase {
+                            user_id: user.id,
+                            username: user.username,
+                        }
+                    }
+                    Ok(_) => self.finish_login(uname),
+                    Err(e) => eprintln!("DB Error logging in: {}", e),
+                }
+            }
+            LoginStep::Passphrase { user_id, username } => {
+                match self.db.check_passphrase(user_id, &input) {
+                    Ok(PassphraseCheck::Ok) => self.finish_login(&username),
+                    Ok(check @ PassphraseCheck::Locked { .. }) => {
+                        self.login_step = LoginStep::Username;
+                        self.login_message = Some(passphrase_message(check, &username));
+                    }
+                    Ok(check) => self.login_message = Some(passphrase_message(check, &username)),
+                    Err(e) => eprintln!("DB Error checking passphrase: {}", e),
+                }
+            }
+            LoginStep::CurrentPassphrase => {
+                let Some(user) = self.user.clone() else {
+                    return;
+                };
+                match self.db.check_passphrase(user.id, &input) {
+                    Ok(PassphraseCheck::Ok) => self.login_step = LoginStep::NewPassphrase,
+                    Ok(check) => {
+                        self.login_message = Some(passphrase_message(check, &user.username))
+                    }
+                    Err(e) => eprintln!("DB Error checking passphrase: {}", e),
+                }
+            }
+            LoginStep::NewPassphrase if input.is_empty() => self.store_passphrase(None),
+            LoginStep::NewPassphrase => {
+                self.login_step = LoginStep::ConfirmPassphrase { first: input };
+            }
+            LoginStep::ConfirmPassphrase { first } if first != input => {
+                self.login_step = LoginStep::NewPassphrase;
+                self.login_message = Some("Passphrases didn't match, try again".to_string());
+            }
+            LoginStep::ConfirmPassphrase { .. } => match auth::hash_passphrase(&input) {
+                Ok(hash) => self.store_passphrase(Some(&hash)),
+                Err(e) => eprintln!("Error hashing passphrase: {}", e),
+            },
         }
-        self.user = uname.to_string();
-        self.login_input.clear();
-        if self.typing.wpm != 0 {
-            let word_count = if self.typing.time_limit.is_some() {
-                (self.typing.user_input.len() as i32) / 5
-            } else {
-                (self.typing.test_text.len() as i32) / 5
-            };
-            self.typing.word_count = word_count;
-            self.db.add_test(
-                uname.clone(),
-                self.typing.wpm,
-                self.typing.raw_wpm,
-                self.typing.accuracy,
-                word_count,
-                self.typing.time_limit.unwrap_or(0) as i32,
-            );
-            self.history = self.db.get_all_tests().expect("error getting all tests");
-            self.current_screen = Screen::Stats;
+    }
+
+    fn finish_login(&mut self, uname: &str) {
+        match self.db.login(uname) {
+            Ok(user) => self.user = Some(user),
+            Err(e) => {
+                eprintln!("DB Error logging in: {}", e);
+                return;
+            }
+        }
+        self.login_step = LoginStep::Username;
+        if let Some(guest_id) = self.claim_after_login.take() {
+            self.claim_guest_test(guest_id);
+            self.current_screen = Screen::History;
         } else {
             self.current_screen = Screen::Main { selected_option: 0 }
         }
     }
+
+    fn store_passphrase(&mut self, hash: Option<&str>) {
+        let Some(user_id) = self.user.as_ref().map(|user| user.id) else {
+            return;
+        };
+        if let Err(e) = self.db.set_passphrase_hash(user_id, hash) {
+            eprintln!("DB Error saving passphrase: {}", e);
+        }
+        if let Ok(user) = self.db.user(user_id) {
+            self.user = Some(user);
+        }
+        self.login_step = LoginStep::Username;
+        self.current_screen = Screen::Main { selected_option: 0 };
+    }
+
+    /// Called once a test is over: records custom text progress, then saves the result for
+    /// the logged in user or keeps it as a guest result.
+    pub fn complete_test(&mut self) {
+        self.typing.metrics = Metrics::compute(&self.typing);
+        if self.typing.mode == TestMode::Custom {
+            if let Some(custom) = &self.custom {
+                let next = (self.custom_section + 1) % custom.len();
+                if let Err(e) = self
+                    .db
+                    .set_custom_position(&custom.hash, &custom.name, next)
+                {
+                    eprintln!("DB Error saving progress: {}", e);
+                }
+            }
+        }
+        if self.user.is_none() {
+            self.save_guest_test();
+        } else {
+            self.save_test();
+        }
+        self.current_screen = Screen::Stats;
+    }
+
+    /// Loads practice text given on the command line and jumps to where it was last left off.
+    pub fn start_custom(&mut self, custom: CustomText) {
+        let saved = self.db.custom_position(&custom.hash).unwrap_or_default();
+        self.custom_section = if saved < custom.len() { saved } else { 0 };
+        self.custom = Some(custom);
+        self.start_custom_section();
+    }
+
+    fn start_custom_section(&mut self) {
+        let Some(section) = self
+            .custom
+            .as_ref()
+            .and_then(|custom| custom.section(self.custom_section))
+            .map(str::to_string)
+        else {
+            return;
+        };
+        self.reset_test();
+        self.typing.test_text = section;
+        self.typing.mode = TestMode::Custom;
+        self.current_screen = Screen::Typing;
+    }
+
+    /// Writes the finished test for the logged in user and reloads the history.
+    pub fn save_test(&mut self) {
+        let Some(user_id) = self.user.as_ref().map(|user| user.id) else {
+            return;
+        };
+        match self.db.add_test(user_id, &self.typing) {
+            Ok(saved) => {
+                self.previous_best = saved.previous_best;
+                self.new_best = saved.new_best;
+            }
+            Err(e) => eprintln!("DB Error saving test: {}", e),
+        }
+        // pick up the totals add_test just bumped
+        if let Ok(user) = self.db.user(user_id) {
+            self.user = Some(user);
+        }
+        if let Some(quote) = &self.typing.quote {
+            self.quote_best = self
+                .db
+                .best_quote_wpm(user_id, &quote.id)
+                .unwrap_or_default();
+        }
+    }
+
+    fn save_guest_test(&mut self) {
+        self.previous_best = None;
+        self.new_best = false;
+        match self.db.add_guest_test(&self.typing) {
+            Ok(guest_id) => self.guest_tests.push((guest_id, self.typing.clone())),
+            Err(e) => eprintln!("DB Error saving guest test: {}", e),
+        }
+    }
+
+    /// Moves a guest result into the logged in user's history.
+    fn claim_guest_test(&mut self, guest_id: i64) {
+        let Some(user_id) = self.user.as_ref().map(|user| user.id) else {
+            return;
+        };
+        let Some(index) = self.guest_tests.iter().position(|(id, _)| *id == guest_id) else {
+            return;
+        };
+        match self
+            .db
+            .claim_guest_test(guest_id, user_id, &self.guest_tests[index].1)
+        {
+            Ok(_) => {
+                self.guest_tests.remove(index);
+            }
+            Err(e) => eprintln!("DB Error claiming guest test: {}", e),
+        }
+        if let Ok(user) = self.db.user(user_id) {
+            self.user = Some(user);
+        }
+        self.refresh_history();
+    }
+
     pub fn handle_test_ops(app: &mut TypeTui, key_event: KeyEvent) {
         match app.test_opts.focus {
             TestOptsFocus::Words => match key_event.code {
@@ -292,6 +771,7 @@ impl TypeTui {
                     app.reset_test();
                     if let Ok(n) = app.test_opts.word_input.trim().parse::<usize>() {
                         app.load_random_words(n);
+                        app.typing.mode = TestMode::Words;
                         app.current_screen = Screen::Typing;
                     }
                 }
@@ -301,15 +781,13 @@ impl TypeTui {
                 _ => {}
             },
             TestOptsFocus::Seconds => match key_event.code {
-                KeyCode::Up => {
-                    if app.test_opts.seconds_selected > 0 {
-                        app.test_opts.seconds_selected -= 1;
-                    }
+                KeyCode::Up if app.test_opts.seconds_selected > 0 => {
+                    app.test_opts.seconds_selected -= 1;
                 }
-                KeyCode::Down => {
-                    if app.test_opts.seconds_selected < app.test_opts.seconds_options.len() - 1 {
-                        app.test_opts.seconds_selected += 1;
-                    }
+                KeyCode::Down
+                    if app.test_opts.seconds_selected < app.test_opts.seconds_options.len() - 1 =>
+                {
+                    app.test_opts.seconds_selected += 1;
                 }
                 KeyCode::Enter => {
                     app.reset_test();
@@ -317,8 +795,92 @@ impl TypeTui {
                         app.test_opts.seconds_options[app.test_opts.seconds_selected];
                     app.load_random_words(50);
                     app.typing.time_limit = Some(chosen_seconds);
+                    app.typing.mode = TestMode::Time;
                     app.current_screen = Screen::Typing;
                 }
+                KeyCode::Tab => {
+                    app.test_opts.focus = TestOptsFocus::Quote;
+                }
+                KeyCode::Esc => app.current_screen = Screen::Main { selected_option: 0 },
+                KeyCode::Char('q') | KeyCode::Char('Q') => app.current_screen = Screen::Quit,
+                _ => {}
+            },
+            TestOptsFocus::Quote => match key_event.code {
+                KeyCode::Up if app.test_opts.quote_selected > 0 => {
+                    app.test_opts.quote_selected -= 1;
+                }
+                KeyCode::Down if app.test_opts.quote_selected < QuoteLength::ALL.len() - 1 => {
+                    app.test_opts.quote_selected += 1;
+                }
+                KeyCode::Enter => {
+                    app.reset_test();
+                    let length = QuoteLength::ALL[app.test_opts.quote_selected];
+                    if app.load_quote(length) {
+                        app.typing.mode = TestMode::Quote;
+                        app.current_screen = Screen::Typing;
+                    }
+                }
+                KeyCode::Tab => {
+                    app.test_opts.focus = TestOptsFocus::Code;
+                }
+                KeyCode::Esc => app.current_screen = Screen::Main { selected_option: 0 },
+                KeyCode::Char('q') | KeyCode::Char('Q') => app.current_screen = Screen::Quit,
+                _ => {}
+            },
+            TestOptsFocus::Code => match key_event.code {
+                KeyCode::Up if app.test_opts.code_selected > 0 => {
+                    app.test_opts.code_selected -= 1;
+                }
+                KeyCode::Down
+                    if app.test_opts.code_selected + 1
+                        < app.test_opts.snippets.languages().len() =>
+                {
+                    app.test_opts.code_selected += 1;
+                }
+                KeyCode::Enter => {
+                    app.reset_test();
+                    let languages = app.test_opts.snippets.languages();
+                    if let Some((language, _)) = languages.get(app.test_opts.code_selected) {
+                        if app.load_snippet(*language) {
+                            app.typing.mode = TestMode::Code;
+                            app.current_screen = Screen::Typing;
+                        }
+                    }
+                }
+                KeyCode::Tab => {
+                    app.test_opts.focus = TestOptsFocus::Options;
+                }
+                KeyCode::Esc => app.current_screen = Screen::Main { selected_option: 0 },
+                KeyCode::Char('q') | KeyCode::Char('Q') => app.current_screen = Screen::Quit,
+                _ => {}
+            },
+            TestOptsFocus::Options => match key_event.code {
+                KeyCode::Up => {
+                    app.test_opts.options_selected =
+                        cycle(app.test_opts.options_selected, TestOption::ALL.len(), false);
+                }
+                KeyCode::Down => {
+                    app.test_opts.options_selected =
+                        cycle(app.test_opts.options_selected, TestOption::ALL.len(), true);
+                }
+                KeyCode::Left | KeyCode::Right | KeyCode::Enter => {
+                    let option = TestOption::ALL[app.test_opts.options_selected];
+                    app.test_opts
+                        .cycle_option(option, key_event.code != KeyCode::Left);
+                }
+                // u64::MAX has 20 digits, 19 always parse
+                KeyCode::Char(c)
+                    if c.is_ascii_digit()
+                        && TestOption::ALL[app.test_opts.options_selected] == TestOption::Seed
+                        && app.test_opts.seed_input.len() < 19 =>
+                {
+                    app.test_opts.seed_input.push(c);
+                }
+                KeyCode::Backspace
+                    if TestOption::ALL[app.test_opts.options_selected] == TestOption::Seed =>
+                {
+                    app.test_opts.seed_input.pop();
+                }
                 KeyCode::Tab => {
                     app.test_opts.focus = TestOptsFocus::Words;
                 }
@@ -329,27 +891,164 @@ impl TypeTui {
         }
     }
 
+    /// Recounts the rows matching the History filters and goes back to the first page.
     pub fn refresh_history(&mut self) {
-        match self.db.get_all_tests() {
-            Ok(rows) => {
-                self.history = rows;
-                self.stats_list_state.select(Some(0));
-            }
+        match self.db.history_count(&self.history_filter) {
+            Ok(total) => self.history_total = total,
+            Err(e) => eprintln!("DB Error counting history: {}", e),
+        }
+        self.load_history_page(HistoryPage::First, 0);
+    }
+
+    /// Fetches a page of History and selects `row`, counted from the first matching row.
+    fn load_history_page(&mut self, page: HistoryPage, row: usize) {
+        let rows = match self.db.history_page(&self.history_filter, page, PAGE_SIZE) {
+            Ok(rows) => rows,
             Err(e) => {
                 eprintln!("DB Error loading history: {}", e);
-                self.history.clear();
+                Vec::new()
+            }
+        };
+        // where the page sits follows from where it was fetched, so it doesn't need counting
+        self.history_start = match page {
+            HistoryPage::First => 0,
+            HistoryPage::Last => self.history_total.saturating_sub(rows.len()),
+            HistoryPage::After(_) => self.history_start + self.history.len(),
+            HistoryPage::Before(_) => self.history_start.saturating_sub(rows.len()),
+        };
+        let selected = row
+            .saturating_sub(self.history_start)
+            .min(rows.len().saturating_sub(1));
+        self.history = rows;
+        self.stats_list_state.select(Some(selected));
+    }
+
+    /// Moves the History selection, fetching the next or previous page when it runs off
+    /// the one on screen. Up from the top wraps to the end and Down from the end to the top.
+    fn scroll_history(&mut self, key: KeyCode) {
+        let index = self.stats_list_state.selected().unwrap_or(0);
+        let row = self.history_start + index;
+        let sort = self.history_filter.sort;
+        let (Some(first), Some(last)) = (self.history.first(), self.history.last()) else {
+            return;
+        };
+        let (first, last) = (first.key(sort), last.key(sort));
+        let on_screen = self.history_start..self.history_start + self.history.len();
+        let last_row = self.history_total.saturating_sub(1);
+        // a page that would run past the top is fetched from the top instead
+        let previous = |start: usize| {
+            if start > PAGE_SIZE {
+                HistoryPage::Before(first)
+            } else {
+                HistoryPage::First
+            }
+        };
+        let (target, page) = match key {
+            KeyCode::Up if row == 0 => (last_row, Some(HistoryPage::Last)),
+            KeyCode::Up => (row - 1, None),
+            KeyCode::Down if row >= last_row => (0, Some(HistoryPage::First)),
+            KeyCode::Down => (row + 1, None),
+            KeyCode::PageUp => (row.saturating_sub(PAGE_SIZE), None),
+            KeyCode::PageDown => ((row + PAGE_SIZE).min(last_row), None),
+            KeyCode::Home => (0, Some(HistoryPage::First)),
+            KeyCode::End => (last_row, Some(HistoryPage::Last)),
+            _ => return,
+        };
+        let page = page.or(if on_screen.contains(&target) {
+            None
+        } else if target < on_screen.start {
+            Some(previous(on_screen.start))
+        } else {
+            Some(HistoryPage::After(last))
+        });
+        match page {
+            Some(page) => self.load_history_page(page, target),
+            None => self
+                .stats_list_state
+                .select(Some(target - self.history_start)),
+        }
+    }
+
+    /// History keys: u, m, l and d step through the user, mode, length and date filters,
+    /// s picks the column to sort by and r reverses the order.
+    fn change_history_filter(&mut self, key: char) {
+        let filter = &mut self.history_filter;
+        match key {
+            'u' => {
+                let mut scopes = vec![UserScope::All];
+                scopes.extend(self.db.users().unwrap_or_default().into_iter().map(|user| {
+                    UserScope::User {
+                        id: user.id,
+                        username: user.username,
+                    }
+                }));
+                if !self.guest_tests.is_empty() {
+                    scopes.push(UserScope::Guest);
+                }
+                filter.user = next_of(&scopes, &filter.user).unwrap_or(UserScope::All);
+            }
+            'm' => {
+                let modes: Vec<Option<&'static str>> = std::iter::once(None)
+                    .chain(history::MODES.into_iter().map(Some))
+                    .collect();
+                filter.mode = next_of(&modes, &filter.mode).flatten();
+                filter.length = None;
+            }
+            'l' => {
+                let Some(mode) = filter.mode else {
+                    return;
+                };
+                let mut lengths: Vec<String> = self
+                    .db
+                    .history_mode_keys(mode)
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|key| history::test_kind(key).to_string())
+                    .collect();
+                // numbers in order, then anything else alphabetically
+                lengths.sort_by_key(|kind| {
+                    let length = kind.split_once(' ').map_or("", |(_, length)| length);
+                    (length.parse::<u32>().unwrap_or(u32::MAX), kind.clone())
+                });
+                lengths.dedup();
+                let lengths: Vec<Option<String>> = std::iter::once(None)
+                    .chain(lengths.into_iter().map(Some))
+                    .collect();
+                filter.length = next_of(&lengths, &filter.length).flatten();
             }
+            'd' => filter.dates = next_of(&DateRange::ALL, &filter.dates).unwrap_or(DateRange::All),
+            's' => filter.sort = next_of(&SortColumn::ALL, &filter.sort).unwrap_or(SortColumn::Wpm),
+            'r' => filter.descending = !filter.descending,
+            _ => return,
         }
+        self.refresh_history();
+    }
+
+    /// Main menu entries, custom text only shows up when some was given on the command line
+    /// and the account entries depend on whether someone is logged in.
+    pub fn menu_items(&self) -> Vec<MenuItem> {
+        let mut items = vec![MenuItem::QuickTest];
+        if self.custom.is_some() {
+            items.push(MenuItem::CustomText);
+        }
+        if self.user.is_some() {
+            items.extend([MenuItem::SwitchUser, MenuItem::Passphrase, MenuItem::LogOut]);
+        } else {
+            items.push(MenuItem::Login);
+        }
+        items.extend([MenuItem::History, MenuItem::Quit, MenuItem::TestOpts]);
+        items
     }
 
     //handle input takes the screen and then the app
     // mutable reference to the app to change state, and a keycode
     pub fn handle_menu_input(key: KeyCode, app: &mut TypeTui) -> Option<io::Result<bool>> {
+        let items = app.menu_items();
         if let Screen::Main {
             ref mut selected_option,
         } = app.current_screen
         {
-            let num_options = 5;
+            let num_options = items.len();
             match key {
                 KeyCode::Up => {
                     if *selected_option == 0 {
@@ -359,7 +1058,7 @@ impl TypeTui {
                     }
                 }
                 KeyCode::Down => {
-                    if *selected_option == num_options - 1 {
+                    if *selected_option >= num_options - 1 {
                         *selected_option = 0;
                     } else {
                         *selected_option += 1;
@@ -367,28 +1066,62 @@ impl TypeTui {
                 }
                 //when the user presses enter we'll check the selected options value and
                 //then switch menu's based on that
-                KeyCode::Enter => match *selected_option {
-                    0 => {
+                KeyCode::Enter => match items.get(*selected_option) {
+                    Some(MenuItem::QuickTest) => {
                         app.reset_test();
                         const DEFAULT_WORD_COUNT: usize = 50;
-                        app.typing.get_words(DEFAULT_WORD_COUNT);
+                        app.load_random_words(DEFAULT_WORD_COUNT);
                         app.typing.time_limit = Some(15);
+                        app.typing.mode = TestMode::Quick;
                         app.current_screen = Screen::Typing
                     }
-                    1 => app.current_screen = Screen::Login,
-                    2 => {
+                    Some(MenuItem::CustomText) => app.start_custom_section(),
+                    Some(MenuItem::Login) | Some(MenuItem::SwitchUser) => {
+                        app.open_login(LoginStep::Username)
+                    }
+                    Some(MenuItem::Passphrase) => {
+                        // read again in case the passphrase was set from another session
+                        let has_passphrase = app
+                            .user
+                            .as_ref()
+                            .and_then(|user| app.db.user(user.id).ok())
+                            .is_some_and(|user| user.has_passphrase);
+                        app.open_login(if has_passphrase {
+                            LoginStep::CurrentPassphrase
+                        } else {
+                            LoginStep::NewPassphrase
+                        })
+                    }
+                    Some(MenuItem::LogOut) => {
+                        if let Err(e) = app.db.logout() {
+                            eprintln!("DB Error logging out: {}", e);
+                        }
+                        app.user = None;
+                        // the entries shift once Log Out is gone
+                        *selected_option = 0;
+                    }
+                    Some(MenuItem::History) => {
+                        // start out on your own results, alongside any from before logging in
+                        // so they can still be claimed
+                        app.history_filter.user = match &app.user {
+                            Some(user) if app.guest_tests.is_empty() => UserScope::User {
+                                id: user.id,
+                                username: user.username.clone(),
+                            },
+                            _ => UserScope::All,
+                        };
                         app.refresh_history();
                         app.current_screen = Screen::History
                     }
-                    3 => {
+                    Some(MenuItem::Quit) => {
                         app.current_screen = Screen::Quit;
                         return Some(Ok(false));
                     }
-                    4 => {
+                    Some(MenuItem::TestOpts) => {
                         app.reset_test();
                         app.current_screen = Screen::TestOpts;
                     }
-                    _ => {}
+                    None => {}
                 },
                 _ => {}
             }
@@ -396,3 +1129,71 @@ impl TypeTui {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// History of `count` saved tests, with speeds shared by many rows.
+    fn with_history(count: i32) -> TypeTui {
+        let mut db = DB::new(std::path::Path::new(":memory:")).unwrap();
+        let user = db.login("ann").unwrap();
+        let mut test = TypingTest::new();
+        for i in 0..count {
+            test.wpm = 40 + i % 7;
+            db.add_test(user.id, &test).unwrap();
+        }
+        let mut app = TypeTui::new(db);
+        app.refresh_history();
+        app
+    }
+
+    /// Row counted from the top and its id, for the selection.
+    fn selected(app: &TypeTui) -> (usize, i64) {
+        let index = app.stats_list_state.selected().unwrap();
+        (app.history_start + index, app.history[index].row_id)
+    }
+
+    #[test]
+    fn scroll_history_crosses_pages() {
+        let mut app = with_history(120);
+        let order: Vec<i64> = app
+            .db
+            .history_page(&app.history_filter, HistoryPage::First, 1000)
+            .unwrap()
+            .iter()
+            .map(|row| row.row_id)
+            .collect();
+        assert_eq!(app.history_total, order.len());
+        let at = |row: usize| (row, order[row]);
+
+        for row in 1..order.len() {
+            app.scroll_history(KeyCode::Down);
+            assert_eq!(selected(&app), at(row));
+        }
+        app.scroll_history(KeyCode::Down);
+        assert_eq!(selected(&app), at(0));
+        app.scroll_history(KeyCode::Up);
+        assert_eq!(selected(&app), at(119));
+        for row in (0..119).rev() {
+            app.scroll_history(KeyCode::Up);
+            assert_eq!(selected(&app), at(row));
+        }
+
+        let keys = [
+            (KeyCode::PageDown, 50),
+            (KeyCode::PageDown, 100),
+            (KeyCode::PageDown, 119),
+            (KeyCode::PageUp, 69),
+            (KeyCode::PageUp, 19),
+            (KeyCode::End, 119),
+            (KeyCode::PageUp, 69),
+            (KeyCode::Home, 0),
+            (KeyCode::PageUp, 0),
+        ];
+        for (key, row) in keys {
+            app.scroll_history(key);
+            assert_eq!(selected(&app), at(row), "{:?}", key);
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+
+/// Wrong passphrases in a row before a user is locked out.
+pub const MAX_FAILED_LOGINS: i32 = 5;
+/// How long a lockout lasts, in seconds.
+pub const LOCKOUT_SECS: i64 = 5 * 60;
+
+/// Salted argon2id hash of a passphrase in PHC string form, parameters included.
+pub fn hash_passphrase(passphrase: &str) -> Result<String, argon2::password_hash::Error> {
+    // rand's thread rng is a CSPRNG seeded from the OS
+    let salt = SaltString::encode_b64(&rand::random::<[u8; 16]>())?;
+    Argon2::default()
+        .hash_password(passphrase.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+}
+
+/// A hash that doesn't parse never matches.
+pub fn verify_passphrase(passphrase: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .map(|parsed| {
+            Argon2::default()
+                .verify_password(passphrase.as_bytes(), &parsed)
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
@@ -0,0 +1,74 @@
+use std::{env, path::PathBuf, process};
+
+use crate::paths::DB_ENV_VAR;
+
+const USAGE: &str = "Usage: typetui [OPTIONS] [FILE]
+
+Arguments:
+  [FILE]  Practice on the text in FILE, use - to read stdin. Piped input is used automatically
+
+Options:
+      --db <PATH>    Use the database at PATH instead of the default location
+      --code <PATH>  Add snippets for code mode from a source file or directory, repeatable
+  -h, --help         Print this help";
+
+/// Command line options.
+#[derive(Debug, Default)]
+pub struct Args {
+    pub db_path: Option<PathBuf>,
+    pub text: Option<TextArg>,
+    pub code_paths: Vec<PathBuf>,
+}
+
+/// Where custom practice text comes from.
+#[derive(Debug, PartialEq, Eq)]
+pub enum TextArg {
+    File(PathBuf),
+    Stdin,
+}
+
+impl Args {
+    /// Parses `std::env::args`, printing usage and exiting on `--help` or bad input.
+    pub fn parse() -> Args {
+        let mut args = Args::default();
+        let mut raw = env::args().skip(1);
+        while let Some(arg) = raw.next() {
+            match arg.as_str() {
+                "-h" | "--help" => {
+                    println!("{}", USAGE);
+                    println!(
+                        "\nThe database location can also be set with ${}.",
+                        DB_ENV_VAR
+                    );
+                    process::exit(0);
+                }
+                "--db" => match raw.next() {
+                    Some(path) => args.db_path = Some(PathBuf::from(path)),
+                    None => usage_error("--db needs a path"),
+                },
+                "--code" => match raw.next() {
+                    Some(path) => args.code_paths.push(PathBuf::from(path)),
+                    None => usage_error("--code needs a path"),
+                },
+                "-" if args.text.is_none() => args.text = Some(TextArg::Stdin),
+                _ => {
+                    if let Some(path) = arg.strip_prefix("--db=") {
+                        args.db_path = Some(PathBuf::from(path));
+                    } else if let Some(path) = arg.strip_prefix("--code=") {
+                        args.code_paths.push(PathBuf::from(path));
+                    } else if !arg.starts_with('-') && args.text.is_none() {
+                        args.text = Some(TextArg::File(PathBuf::from(arg)));
+                    } else {
+                        usage_error(&format!("unexpected argument '{}'", arg));
+                    }
+                }
+            }
+        }
+        args
+    }
+}
+
+fn usage_error(msg: &str) -> ! {
+    eprintln!("error: {}\n\n{}", msg, USAGE);
+    process::exit(2);
+}
@@ -0,0 +1,213 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use rand::{seq::IndexedRandom, Rng};
+
+use crate::paths;
+
+/// Snippets compiled into the binary so code mode works out of the box.
+const BUILTIN_FILES: &[(&str, &str)] = &[
+    ("rust.rs", include_str!("../snippets/rust.rs")),
+    ("python.py", include_str!("../snippets/python.py")),
+    ("javascript.js", include_str!("../snippets/javascript.js")),
+];
+
+/// Longer blocks are skipped, they don't fit on the typing screen.
+const MAX_SNIPPET_LINES: usize = 25;
+pub const TAB_WIDTH: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Language {
+    Rust,
+    Python,
+    JavaScript,
+    Go,
+    C,
+    Other,
+}
+
+impl Language {
+    pub fn from_path(path: &Path) -> Language {
+        match path.extension().and_then(|e| e.to_str()).unwrap_or("") {
+            "rs" => Language::Rust,
+            "py" => Language::Python,
+            "js" | "jsx" | "ts" | "tsx" | "mjs" => Language::JavaScript,
+            "go" => Language::Go,
+            "c" | "h" | "cc" | "cpp" | "hpp" | "java" => Language::C,
+            _ => Language::Other,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Language::Rust => "rust",
+            Language::Python => "python",
+            Language::JavaScript => "javascript",
+            Language::Go => "go",
+            Language::C => "c",
+            Language::Other => "other",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Snippet {
+    pub language: Language,
+    /// File the snippet was cut from.
+    pub source: String,
+    /// Newlines and indentation are kept, tabs are expanded to spaces.
+    pub text: String,
+}
+
+/// Every snippet available to code mode, loaded once at startup.
+#[derive(Clone, Debug, Default)]
+pub struct SnippetBook {
+    snippets: Vec<Arc<Snippet>>,
+}
+
+impl SnippetBook {
+    /// Bundled snippets, files in `<config dir>/code`, and any paths from `--code`.
+    /// Directories are read one level deep, unreadable files are skipped.
+    pub fn load(extra: &[PathBuf]) -> SnippetBook {
+        let mut book = SnippetBook::default();
+        for (name, contents) in BUILTIN_FILES {
+            book.add_file(Path::new(name), contents);
+        }
+        let config = paths::config_dir().join("code");
+        for path in std::iter::once(config.as_path()).chain(extra.iter().map(PathBuf::as_path)) {
+            if path.is_dir() {
+                let mut files: Vec<_> = fs::read_dir(path)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|entry| entry.ok().map(|e| e.path()))
+                    .filter(|p| p.is_file())
+                    .collect();
+                files.sort();
+                for file in files {
+                    book.add_path(&file);
+                }
+            } else {
+                book.add_path(path);
+            }
+        }
+        book
+    }
+
+    fn add_path(&mut self, path: &Path) {
+        if let Ok(contents) = fs::read_to_string(path) {
+            self.add_file(path, &contents);
+        }
+    }
+
+    fn add_file(&mut self, path: &Path, contents: &str) {
+        let language = Language::from_path(path);
+        let source = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        self.snippets
+            .extend(split_snippets(contents).into_iter().map(|text| {
+                Arc::new(Snippet {
+                    language,
+                    source: source.clone(),
+                    text,
+                })
+            }));
+    }
+
+    /// Languages that have at least one snippet, with their snippet counts.
+    pub fn languages(&self) -> Vec<(Language, usize)> {
+        let mut languages: Vec<(Language, usize)> = Vec::new();
+        for snippet in &self.snippets {
+            match languages.iter_mut().find(|(l, _)| *l == snippet.language) {
+                Some((_, count)) => *count += 1,
+                None => languages.push((snippet.language, 1)),
+            }
+        }
+        languages.sort();
+        languages
+    }
+
+    pub fn pick<R: Rng + ?Sized>(&self, rng: &mut R, language: Language) -> Option<Arc<Snippet>> {
+        let matching: Vec<&Arc<Snippet>> = self
+            .snippets
+            .iter()
+            .filter(|s| s.language == language)
+            .collect();
+        matching.choose(rng).map(|s| Arc::clone(s))
+    }
+}
+
+// a snippet ends at a blank line followed by an unindented line, so methods inside a class or
+// impl block stay together
+fn split_snippets(contents: &str) -> Vec<String> {
+    let lines: Vec<String> = contents
+        .lines()
+        .map(|line| {
+            line.replace('\t', &" ".repeat(TAB_WIDTH))
+                .trim_end()
+                .to_string()
+        })
+        .collect();
+    let mut snippets = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        let next_top_level = lines
+            .get(i + 1)
+            .is_some_and(|next| !next.is_empty() && !next.starts_with(' '));
+        if line.is_empty() && (current.is_empty() || next_top_level) {
+            push_snippet(&mut snippets, &current);
+            current.clear();
+        } else {
+            current.push(line);
+        }
+    }
+    push_snippet(&mut snippets, &current);
+    snippets
+}
+
+fn push_snippet(snippets: &mut Vec<String>, lines: &[&str]) {
+    let lines: Vec<&str> = trim_blank(lines);
+    if lines.len() < 2 || lines.len() > MAX_SNIPPET_LINES {
+        return;
+    }
+    let indent = lines
+        .iter()
+        .filter(|l| !l.is_empty())
+        // only spaces count as indentation, tabs are already expanded
+        .map(|l| l.len() - l.trim_start_matches(' ').len())
+        .min()
+        .unwrap_or(0);
+    let dedented: Vec<&str> = lines
+        .iter()
+        .map(|l| if l.is_empty() { l } else { &l[indent..] })
+        .collect();
+    snippets.push(dedented.join("\n"));
+}
+
+fn trim_blank<'a>(lines: &[&'a str]) -> Vec<&'a str> {
+    let start = lines
+        .iter()
+        .position(|l| !l.is_empty())
+        .unwrap_or(lines.len());
+    let end = lines
+        .iter()
+        .rposition(|l| !l.is_empty())
+        .map_or(start, |i| i + 1);
+    lines[start..end].to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dedents_by_common_spaces() {
+        assert_eq!(split_snippets("  a\n    b"), ["a\n  b"]);
+        // other whitespace is left alone rather than cut in half
+        assert_eq!(split_snippets(" a\n\u{a0}b\n c"), [" a\n\u{a0}b\n c"]);
+    }
+}
@@ -0,0 +1,92 @@
+use std::{
+    fs,
+    io::{self, Read},
+    path::Path,
+};
+
+/// Sections end at the first sentence break after this many words...
+const SECTION_MIN_WORDS: usize = 40;
+/// ...or are cut here when the text has no punctuation to break on.
+const SECTION_MAX_WORDS: usize = 80;
+
+/// User supplied text from a file or stdin, split into sections short enough for one test.
+#[derive(Clone, Debug)]
+pub struct CustomText {
+    /// File name, or `stdin`.
+    pub name: String,
+    /// Identifies the text in the database so progress survives renames.
+    pub hash: String,
+    sections: Vec<String>,
+}
+
+impl CustomText {
+    pub fn from_file(path: &Path) -> io::Result<CustomText> {
+        let contents = fs::read_to_string(path)?;
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+        CustomText::new(name, &contents)
+    }
+
+    pub fn from_stdin() -> io::Result<CustomText> {
+        let mut contents = String::new();
+        io::stdin().read_to_string(&mut contents)?;
+        CustomText::new("stdin".to_string(), &contents)
+    }
+
+    fn new(name: String, contents: &str) -> io::Result<CustomText> {
+        let sections = split_sections(contents);
+        if sections.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} has no text to type", name),
+            ));
+        }
+        Ok(CustomText {
+            name,
+            hash: fnv1a_hex(contents.as_bytes()),
+            sections,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.sections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+    }
+
+    pub fn section(&self, index: usize) -> Option<&str> {
+        self.sections.get(index).map(String::as_str)
+    }
+}
+
+// whitespace is collapsed, the typing screen shows a single wrapped paragraph
+fn split_sections(contents: &str) -> Vec<String> {
+    let mut sections = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    for word in contents.split_whitespace() {
+        current.push(word);
+        let sentence_end = word.ends_with(['.', '?', '!']);
+        if (current.len() >= SECTION_MIN_WORDS && sentence_end)
+            || current.len() >= SECTION_MAX_WORDS
+        {
+            sections.push(current.join(" "));
+            current.clear();
+        }
+    }
+    if !current.is_empty() {
+        sections.push(current.join(" "));
+    }
+    sections
+}
+
+// a fixed algorithm rather than `DefaultHasher`, whose output may change between Rust releases
+fn fnv1a_hex(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
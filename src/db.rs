@@ -1,76 +1,830 @@
-use rusqlite::{params, Connection, Result};
+use std::{
+    error::Error,
+    fmt, fs, io,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rusqlite::{params, Connection, OptionalExtension, Result, Transaction};
+
+use rusqlite::types::Value;
+
+use crate::{
+    auth,
+    history::{HistoryFilter, HistoryKey, HistoryPage, SortColumn, UserScope},
+    migrations,
+    typingtest::TypingTest,
+};
+
+/// A row of the history table.
+#[derive(Clone, Debug)]
+pub struct TestRow {
+    /// Unique across saved and guest results, guest ones are negative.
+    pub row_id: i64,
+    /// Set for results typed as a guest this session, they can still be claimed.
+    pub guest_id: Option<i64>,
+    pub username: String,
+    pub wpm: i32,
+    pub raw_wpm: i32,
+    pub accuracy: i32,
+    pub created_at: Option<i64>,
+    /// Local time the test was finished, missing for tests from before that was recorded.
+    pub date: Option<String>,
+    pub mode_key: String,
+}
+
+impl TestRow {
+    pub fn key(&self, sort: SortColumn) -> HistoryKey {
+        let value = match sort {
+            SortColumn::Date => self.created_at.unwrap_or(0),
+            SortColumn::Wpm => self.wpm as i64,
+            SortColumn::RawWpm => self.raw_wpm as i64,
+            SortColumn::Accuracy => self.accuracy as i64,
+        };
+        HistoryKey {
+            value,
+            row_id: self.row_id,
+        }
+    }
+}
+
+/// A row of the users table.
+#[derive(Clone, Debug)]
+pub struct User {
+    pub id: i64,
+    pub username: String,
+    pub total_tests: i32,
+    pub top_wpm: Option<i32>,
+    pub has_passphrase: bool,
+}
+
+/// Tests from before mode keys were recorded get one from their time limit when they had one.
+const SAVED_MODE_KEY: &str =
+    "coalesce(tests.mode_key, CASE WHEN tests.time > 0 THEN 'time ' || tests.time ELSE 'words' END)";
+
+/// Where History rows come from. Each is queried on its own so saved tests are read in order
+/// off the index for the sort, and only the everyone scope merges the two. Guest results are
+/// a handful per session and simply sorted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum HistorySource {
+    Saved,
+    Guest,
+}
+
+impl HistorySource {
+    fn for_scope(user: &UserScope) -> &'static [HistorySource] {
+        match user {
+            UserScope::All => &[HistorySource::Saved, HistorySource::Guest],
+            UserScope::User { .. } => &[HistorySource::Saved],
+            UserScope::Guest => &[HistorySource::Guest],
+        }
+    }
+
+    fn from(self) -> &'static str {
+        match self {
+            HistorySource::Saved => "tests JOIN users ON users.id = tests.user_id",
+            HistorySource::Guest => "temp.guest_tests",
+        }
+    }
+
+    /// Prefix for columns both tables have.
+    fn table(self) -> &'static str {
+        match self {
+            HistorySource::Saved => "tests.",
+            HistorySource::Guest => "",
+        }
+    }
+
+    /// Unique across both sources, guest ones are negative.
+    fn row_id(self) -> &'static str {
+        match self {
+            HistorySource::Saved => "tests.id",
+            HistorySource::Guest => "-id",
+        }
+    }
+
+    fn mode_key(self) -> &'static str {
+        match self {
+            HistorySource::Saved => SAVED_MODE_KEY,
+            HistorySource::Guest => "mode_key",
+        }
+    }
+
+    /// What rows are ordered by, the same expressions the v13 and v14 indexes are built on.
+    /// Missing values sort as 0, keys have to be comparable for paging to work.
+    fn sort_value(self, sort: SortColumn) -> String {
+        let column = format!("{}{}", self.table(), sort.column());
+        match sort {
+            SortColumn::Date | SortColumn::RawWpm => format!("coalesce({}, 0)", column),
+            SortColumn::Wpm | SortColumn::Accuracy => column,
+        }
+    }
+
+    /// Columns in the order `history_page` reads them. Raw speed is read as it sorts, the
+    /// first tests were saved without it.
+    fn select(self, sort: SortColumn) -> String {
+        let (guest_id, username) = match self {
+            HistorySource::Saved => ("NULL", "users.username"),
+            HistorySource::Guest => ("id", "'guest'"),
+        };
+        format!(
+            "{row_id} AS row_id, {guest_id} AS guest_id, {username} AS username,
+             {t}wpm AS wpm, {raw_wpm} AS raw_wpm, {t}accuracy AS accuracy,
+             {t}created_at AS created_at, {mode_key} AS mode_key, {sort} AS sort_value",
+            row_id = self.row_id(),
+            guest_id = guest_id,
+            username = username,
+            t = self.table(),
+            raw_wpm = self.sort_value(SortColumn::RawWpm),
+            mode_key = self.mode_key(),
+            sort = self.sort_value(sort)
+        )
+    }
+
+    /// WHERE conditions for the History filters, binding their values onto `values`.
+    fn conditions(self, filter: &HistoryFilter, values: &mut Vec<Value>) -> Vec<String> {
+        let mut conditions = Vec::new();
+        if let (HistorySource::Saved, UserScope::User { id, .. }) = (self, &filter.user) {
+            values.push(Value::Integer(*id));
+            conditions.push(format!("tests.user_id = ?{}", values.len()));
+        }
+        // a mode or length matches the start of the key, up to a word boundary
+        let prefix = filter.length.as_deref().or(filter.mode);
+        if let Some(prefix) = prefix {
+            let separator = if filter.length.is_some() { " · " } else { " " };
+            values.push(Value::Text(prefix.to_string()));
+            values.push(Value::Text(format!("{}{}%", prefix, separator)));
+            conditions.push(format!(
+                "({mode_key} = ?{} OR {mode_key} LIKE ?{})",
+                values.len() - 1,
+                values.len(),
+                mode_key = self.mode_key()
+            ));
+        }
+        if let Some(since) = filter.dates.since_sql() {
+            conditions.push(format!(
+                "{}created_at >= CAST(strftime('%s', {}) AS integer)",
+                self.table(),
+                since
+            ));
+        }
+        conditions
+    }
+}
+
+/// Columns `user_from_row` reads, in order.
+const USER_COLUMNS: &str = "id, username, total_tests, top_wpm, passphrase_hash IS NOT NULL";
+
+/// Outcome of `check_passphrase`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PassphraseCheck {
+    Ok,
+    Wrong { tries_left: i32 },
+    Locked { secs_left: i64 },
+}
+
+/// What `add_test` wrote.
+#[derive(Clone, Copy, Debug)]
+pub struct SavedTest {
+    /// The user's best WPM on the same kind of test before this one.
+    pub previous_best: Option<i32>,
+    pub new_best: bool,
+}
 
 #[derive(Debug)]
 pub struct DB {
     pub conn: rusqlite::Connection,
 }
 
+/// Errors that can happen while opening the database.
+#[derive(Debug)]
+pub enum DbError {
+    Sqlite(rusqlite::Error),
+    Io(io::Error),
+    /// The file was written by a newer typetui, opening it could corrupt its data.
+    NewerSchema {
+        found: i64,
+        supported: i64,
+    },
+}
+
+impl fmt::Display for DbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbError::Sqlite(e) => write!(f, "database error: {}", e),
+            DbError::Io(e) => write!(f, "{}", e),
+            DbError::NewerSchema { found, supported } => write!(
+                f,
+                "database schema version {} is newer than this build supports ({}), please upgrade typetui",
+                found, supported
+            ),
+        }
+    }
+}
+
+impl Error for DbError {}
+
+impl From<rusqlite::Error> for DbError {
+    fn from(e: rusqlite::Error) -> Self {
+        DbError::Sqlite(e)
+    }
+}
+
+impl From<io::Error> for DbError {
+    fn from(e: io::Error) -> Self {
+        DbError::Io(e)
+    }
+}
+
 impl DB {
-    pub fn new() -> Result<DB> {
-        let db_con = match Connection::open("typetui.db") {
-            Ok(c) => c,
-            Err(e) => {
-                eprintln!("Error opening or creating database: {}", e);
-                return Err(e);
+    /// Opens the database at `path`, creating it and its directory on first run.
+    pub fn new(path: &Path) -> std::result::Result<DB, DbError> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let mut conn = Connection::open(path)?;
+        conn.execute_batch("PRAGMA foreign_keys = ON")?;
+        migrations::run(&mut conn)?;
+        // guest results only live as long as the connection, so this isn't a migration
+        conn.execute_batch(
+            "CREATE TEMP TABLE guest_tests (
+                id INTEGER PRIMARY KEY,
+                wpm INTEGER NOT NULL,
+                raw_wpm INTEGER NOT NULL,
+                accuracy INTEGER NOT NULL,
+                word_count INTEGER NOT NULL,
+                time INTEGER NOT NULL,
+                created_at INTEGER NOT NULL,
+                mode_key TEXT NOT NULL
+            )",
+        )?;
+        Ok(DB { conn })
+    }
+
+    /// Saves a finished test with its keystroke log, speed samples and word bursts, and
+    /// records it as a personal best when it beats the previous one.
+    pub fn add_test(&mut self, user_id: i64, test: &TypingTest) -> Result<SavedTest> {
+        let tx = self.conn.transaction()?;
+        let saved = insert_test(&tx, user_id, test, unix_now())?;
+        tx.commit()?;
+        Ok(saved)
+    }
+
+    /// Keeps a result typed without logging in for the rest of the session.
+    pub fn add_guest_test(&mut self, test: &TypingTest) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO temp.guest_tests (wpm, raw_wpm, accuracy, word_count, time, created_at, mode_key)
+             values (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                test.wpm,
+                test.raw_wpm,
+                test.accuracy,
+                test.word_count,
+                test.time_limit.unwrap_or(0) as i32,
+                unix_now(),
+                test.mode_key()
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Saves a guest result for `user_id` as if they had typed it when it was finished.
+    pub fn claim_guest_test(
+        &mut self,
+        guest_id: i64,
+        user_id: i64,
+        test: &TypingTest,
+    ) -> Result<SavedTest> {
+        // claimed exactly once, the result moves in a single transaction
+        let tx = self.conn.transaction()?;
+        let created_at: i64 = tx.query_row(
+            "SELECT created_at FROM temp.guest_tests WHERE id = ?1",
+            [guest_id],
+            |row| row.get(0),
+        )?;
+        let saved = insert_test(&tx, user_id, test, created_at)?;
+        tx.execute("DELETE FROM temp.guest_tests WHERE id = ?1", [guest_id])?;
+        tx.commit()?;
+        Ok(saved)
+    }
+
+    /// The user's best WPM on a quote, `None` if they've never typed it.
+    pub fn best_quote_wpm(&self, user_id: i64, quote_id: &str) -> Result<Option<i32>> {
+        self.conn.query_row(
+            "SELECT MAX(wpm) FROM tests WHERE user_id = ?1 AND quote_id = ?2",
+            params![user_id, quote_id],
+            |row| row.get(0),
+        )
+    }
+
+    /// Looks up the user, creating them on first login, and remembers them for the next launch.
+    pub fn login(&mut self, username: &str) -> Result<User> {
+        let now = unix_now();
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT INTO users (username, total_tests, created_at) values (?1, 0, ?2)
+             ON CONFLICT(username) DO NOTHING",
+            params![username, now],
+        )?;
+        tx.execute(
+            "UPDATE users SET last_login_at = ?2 WHERE username = ?1",
+            params![username, now],
+        )?;
+        let user = tx.query_row(
+            &format!("SELECT {} FROM users WHERE username = ?1", USER_COLUMNS),
+            [username],
+            user_from_row,
+        )?;
+        tx.execute(
+            "INSERT INTO settings (key, value) values ('last_user', ?1)
+             ON CONFLICT(key) DO UPDATE SET value = ?1",
+            [user.id.to_string()],
+        )?;
+        tx.commit()?;
+        Ok(user)
+    }
+
+    /// Whoever was logged in when typetui last closed.
+    pub fn last_user(&self) -> Result<Option<User>> {
+        self.conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM users
+                      WHERE id = (SELECT CAST(value AS integer) FROM settings WHERE key = 'last_user')",
+                    USER_COLUMNS
+                ),
+                [],
+                user_from_row,
+            )
+            .optional()
+    }
+
+    pub fn user(&self, id: i64) -> Result<User> {
+        self.conn.query_row(
+            &format!("SELECT {} FROM users WHERE id = ?1", USER_COLUMNS),
+            [id],
+            user_from_row,
+        )
+    }
+
+    pub fn user_by_name(&self, username: &str) -> Result<Option<User>> {
+        self.conn
+            .query_row(
+                &format!("SELECT {} FROM users WHERE username = ?1", USER_COLUMNS),
+                [username],
+                user_from_row,
+            )
+            .optional()
+    }
+
+    /// Checks a passphrase, counting failures towards a lockout. Users without a passphrase
+    /// always pass.
+    pub fn check_passphrase(&mut self, user_id: i64, passphrase: &str) -> Result<PassphraseCheck> {
+        let now = unix_now();
+        let tx = self.conn.transaction()?;
+        let (hash, failed, locked_until): (Option<String>, i32, Option<i64>) = tx.query_row(
+            "SELECT passphrase_hash, failed_logins, locked_until FROM users WHERE id = ?1",
+            [user_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        if let Some(until) = locked_until.filter(|until| *until > now) {
+            return Ok(PassphraseCheck::Locked {
+                secs_left: until - now,
+            });
+        }
+        let matches = hash.is_none_or(|hash| auth::verify_passphrase(passphrase, &hash));
+        let check = if matches {
+            tx.execute(
+                "UPDATE users SET failed_logins = 0, locked_until = NULL WHERE id = ?1",
+                [user_id],
+            )?;
+            PassphraseCheck::Ok
+        } else if failed + 1 >= auth::MAX_FAILED_LOGINS {
+            // the count starts over once the lockout has run out
+            tx.execute(
+                "UPDATE users SET failed_logins = 0, locked_until = ?2 WHERE id = ?1",
+                params![user_id, now + auth::LOCKOUT_SECS],
+            )?;
+            PassphraseCheck::Locked {
+                secs_left: auth::LOCKOUT_SECS,
+            }
+        } else {
+            tx.execute(
+                "UPDATE users SET failed_logins = ?2 WHERE id = ?1",
+                params![user_id, failed + 1],
+            )?;
+            PassphraseCheck::Wrong {
+                tries_left: auth::MAX_FAILED_LOGINS - failed - 1,
             }
         };
-        let db = DB { conn: db_con };
-        let _ = db.conn.execute(
-            "CREATE TABLE if not exists users (
-        id integer primary key,
-        username text not null unique,
-        total_tests integer,
-        top_wpm integer)",
-            [],
-        );
+        tx.commit()?;
+        Ok(check)
+    }
 
-        let _ = db.conn.execute(
-            "CREATE TABLE if not exists tests (
-        id integer primary key,
-        username text not null ,
-        wpm integer not null ,
-        raw_wpm integer,
-        accuracy integer not null,
-        word_count integer not null,
-        time integer not null)",
-            [],
-        );
-        Ok(db)
+    /// Stores a hash from `auth::hash_passphrase`, `None` removes the passphrase.
+    pub fn set_passphrase_hash(&mut self, user_id: i64, hash: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE users SET passphrase_hash = ?2, failed_logins = 0, locked_until = NULL
+              WHERE id = ?1",
+            params![user_id, hash],
+        )?;
+        Ok(())
     }
-    pub fn add_test(
-        &mut self,
-        username: String,
-        wpm: i32,
-        raw_wpm: i32,
-        accuracy: i32,
-        word_count: i32,
-        time: i32,
-    ) {
-        let _ = self.conn.execute(
-            "INSERT INTO tests (username, wpm, raw_wpm, accuracy, word_count, time) values (?1, ?2, ?3, ?4, ?5, ?6)",
-            params![username, wpm,raw_wpm, accuracy, word_count, time],
-        );
+
+    pub fn logout(&mut self) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM settings WHERE key = 'last_user'", [])?;
+        Ok(())
+    }
+
+    /// Section to resume a custom text at, 0 for text we haven't seen.
+    pub fn custom_position(&self, hash: &str) -> Result<usize> {
+        self.conn
+            .query_row(
+                "SELECT section FROM custom_progress WHERE hash = ?1",
+                [hash],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()
+            .map(|section| section.unwrap_or(0) as usize)
     }
-    pub fn get_all_tests(&self) -> Result<Vec<(String, i32, i32, i32, i32, i32)>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT username, wpm, raw_wpm, accuracy, word_count, time
-           FROM tests
-          ORDER BY wpm DESC",
+
+    pub fn set_custom_position(&mut self, hash: &str, name: &str, section: usize) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO custom_progress (hash, name, section, updated_at) values (?1, ?2, ?3, ?4)
+             ON CONFLICT(hash) DO UPDATE SET name = ?2, section = ?3, updated_at = ?4",
+            params![hash, name, section as i64, unix_now()],
         )?;
-        let rows = stmt.query_map([], |row| {
-            Ok((
-                row.get::<_, String>(0)?,
-                row.get::<_, i32>(1)?,
-                row.get::<_, i32>(2)?,
-                row.get::<_, i32>(3)?,
-                row.get::<_, i32>(4)?,
-                row.get::<_, i32>(5)?,
-            ))
+        Ok(())
+    }
+
+    /// A page of results matching the History screen's filters, in its chosen order. Pages
+    /// are found from the key of a row next to them rather than an offset, and every sort has
+    /// an index for one user and for everyone, so a deep page costs about as much as the
+    /// first. Mode and date filters are checked while walking that index.
+    pub fn history_page(
+        &self,
+        filter: &HistoryFilter,
+        page: HistoryPage,
+        limit: usize,
+    ) -> Result<Vec<TestRow>> {
+        // pages running up to a row are read backwards from it, then flipped
+        let backwards = matches!(page, HistoryPage::Last | HistoryPage::Before(_));
+        let direction = if filter.descending != backwards {
+            "DESC"
+        } else {
+            "ASC"
+        };
+        let mut values = Vec::new();
+        let mut branches = Vec::new();
+        for source in HistorySource::for_scope(&filter.user) {
+            let mut conditions = source.conditions(filter, &mut values);
+            match page {
+                HistoryPage::After(key) => {
+                    conditions.push(key_condition(*source, filter, key, true, &mut values))
+                }
+                HistoryPage::Before(key) => {
+                    conditions.push(key_condition(*source, filter, key, false, &mut values))
+                }
+                HistoryPage::First | HistoryPage::Last => {}
+            }
+            values.push(Value::Integer(limit as i64));
+            // each source is cut down to a page before they're merged
+            branches.push(format!(
+                "SELECT * FROM (SELECT {} FROM {} {} ORDER BY {} {dir}, {} {dir} LIMIT ?{})",
+                source.select(filter.sort),
+                source.from(),
+                where_clause(&conditions),
+                source.sort_value(filter.sort),
+                source.row_id(),
+                values.len(),
+                dir = direction
+            ));
+        }
+        values.push(Value::Integer(limit as i64));
+        let sql = format!(
+            "SELECT row_id, guest_id, username, wpm, raw_wpm, accuracy, created_at,
+                    strftime('%Y-%m-%d %H:%M', created_at, 'unixepoch', 'localtime'), mode_key
+               FROM ({})
+              ORDER BY sort_value {dir}, row_id {dir}
+              LIMIT ?{}",
+            branches.join(" UNION ALL "),
+            values.len(),
+            dir = direction
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(values), |row| {
+            Ok(TestRow {
+                row_id: row.get(0)?,
+                guest_id: row.get(1)?,
+                username: row.get(2)?,
+                wpm: row.get(3)?,
+                raw_wpm: row.get(4)?,
+                accuracy: row.get(5)?,
+                created_at: row.get(6)?,
+                date: row.get(7)?,
+                mode_key: row.get(8)?,
+            })
         })?;
-        let mut results = Vec::new();
-        for r in rows {
-            results.push(r?);
+        let mut rows = rows.collect::<Result<Vec<_>>>()?;
+        if backwards {
+            rows.reverse();
+        }
+        Ok(rows)
+    }
+
+    /// How many results match the History filters.
+    pub fn history_count(&self, filter: &HistoryFilter) -> Result<usize> {
+        let mut total = 0;
+        for source in HistorySource::for_scope(&filter.user) {
+            let mut values = Vec::new();
+            let conditions = source.conditions(filter, &mut values);
+            let sql = format!(
+                "SELECT count(*) FROM {} {}",
+                source.from(),
+                where_clause(&conditions)
+            );
+            total += self
+                .conn
+                .query_row(&sql, rusqlite::params_from_iter(values), |row| {
+                    row.get::<_, i64>(0)
+                })? as usize;
+        }
+        Ok(total)
+    }
+
+    /// Mode keys recorded for a mode, for picking a length to filter on.
+    pub fn history_mode_keys(&self, mode: &str) -> Result<Vec<String>> {
+        let mut keys = Vec::new();
+        for source in HistorySource::for_scope(&UserScope::All) {
+            let mut stmt = self.conn.prepare(&format!(
+                "SELECT DISTINCT {key} FROM {} WHERE {key} LIKE ?1",
+                source.from(),
+                key = source.mode_key()
+            ))?;
+            let rows = stmt.query_map([format!("{} %", mode)], |row| row.get(0))?;
+            for key in rows {
+                keys.push(key?);
+            }
+        }
+        Ok(keys)
+    }
+
+    pub fn users(&self) -> Result<Vec<User>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM users ORDER BY username",
+            USER_COLUMNS
+        ))?;
+        let rows = stmt.query_map([], user_from_row)?;
+        rows.collect()
+    }
+}
+
+/// Writes a test and everything recorded with it, see `DB::add_test`.
+fn insert_test(
+    tx: &Transaction,
+    user_id: i64,
+    test: &TypingTest,
+    created_at: i64,
+) -> Result<SavedTest> {
+    let mode_key = test.mode_key();
+    tx.execute(
+        "INSERT INTO tests (user_id, wpm, raw_wpm, accuracy, word_count, time, created_at, mode, test_text, user_input, seed, word_list, band, punctuation, numbers, quote_id, consistency, correct_chars, incorrect_chars, extra_chars, missed_chars, mode_key)
+         values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22)",
+        params![
+            user_id,
+            test.wpm,
+            test.raw_wpm,
+            test.accuracy,
+            test.word_count,
+            test.time_limit.unwrap_or(0) as i32,
+            created_at,
+            test.mode.as_str(),
+            test.test_text,
+            test.user_input,
+            // sqlite integers are signed, the bits round-trip through `as`
+            test.seed as i64,
+            test.word_list.name,
+            test.band.as_str(),
+            test.punctuation,
+            test.numbers,
+            test.quote.as_ref().map(|q| q.id.as_str()),
+            test.metrics.consistency,
+            test.metrics.chars.correct,
+            test.metrics.chars.incorrect,
+            test.metrics.chars.extra,
+            test.metrics.chars.missed,
+            mode_key
+        ],
+    )?;
+    let test_id = tx.last_insert_rowid();
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO keystrokes (test_id, seq, typed, expected, offset_ms, kind)
+             values (?1, ?2, ?3, ?4, ?5, ?6)",
+        )?;
+        for (seq, key) in test.keystrokes.iter().enumerate() {
+            stmt.execute(params![
+                test_id,
+                seq as i64,
+                key.typed.to_string(),
+                key.expected.map(|c| c.to_string()),
+                key.offset.as_millis() as i64,
+                key.kind.as_str()
+            ])?;
+        }
+        let mut stmt = tx.prepare(
+            "INSERT INTO test_samples (test_id, second, wpm, raw_wpm, errors)
+             values (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for sample in &test.metrics.samples {
+            stmt.execute(params![
+                test_id,
+                sample.second,
+                sample.wpm,
+                sample.raw_wpm,
+                sample.errors
+            ])?;
+        }
+        let mut stmt = tx.prepare(
+            "INSERT INTO test_words (test_id, seq, word, typed, burst_wpm)
+             values (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for (seq, word) in test.metrics.words.iter().enumerate() {
+            stmt.execute(params![
+                test_id,
+                seq as i64,
+                word.word,
+                word.typed,
+                word.burst_wpm
+            ])?;
+        }
+    }
+    tx.execute(
+        "UPDATE users SET total_tests = coalesce(total_tests, 0) + 1,
+                top_wpm = max(coalesce(top_wpm, 0), ?2), last_test_at = ?3
+          WHERE id = ?1",
+        params![user_id, test.wpm, created_at],
+    )?;
+    let previous_best: Option<i32> = tx
+        .query_row(
+            "SELECT wpm FROM personal_bests WHERE user_id = ?1 AND mode_key = ?2",
+            params![user_id, mode_key],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let new_best = previous_best.is_none_or(|best| test.wpm > best);
+    if new_best {
+        tx.execute(
+            "INSERT INTO personal_bests (user_id, mode_key, wpm, accuracy, test_id, achieved_at)
+             values (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(user_id, mode_key) DO UPDATE
+             SET wpm = ?3, accuracy = ?4, test_id = ?5, achieved_at = ?6",
+            params![
+                user_id,
+                mode_key,
+                test.wpm,
+                test.accuracy,
+                test_id,
+                created_at
+            ],
+        )?;
+    }
+    Ok(SavedTest {
+        previous_best,
+        new_best,
+    })
+}
+
+/// Rows listed after `key` in the History order, or before it.
+fn key_condition(
+    source: HistorySource,
+    filter: &HistoryFilter,
+    key: HistoryKey,
+    after: bool,
+    values: &mut Vec<Value>,
+) -> String {
+    values.push(Value::Integer(key.value));
+    values.push(Value::Integer(key.row_id));
+    let operator = if filter.descending == after { "<" } else { ">" };
+    // spelled out rather than as a row value so the sort value bounds an index range
+    format!(
+        "{sort} {op}= ?{value} AND ({sort} {op} ?{value} OR {row_id} {op} ?{key_row})",
+        sort = source.sort_value(filter.sort),
+        op = operator,
+        row_id = source.row_id(),
+        value = values.len() - 1,
+        key_row = values.len()
+    )
+}
+
+fn where_clause(conditions: &[String]) -> String {
+    if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    }
+}
+
+fn user_from_row(row: &rusqlite::Row) -> Result<User> {
+    Ok(User {
+        id: row.get(0)?,
+        username: row.get(1)?,
+        total_tests: row.get::<_, Option<i32>>(2)?.unwrap_or(0),
+        top_wpm: row.get(3)?,
+        has_passphrase: row.get(4)?,
+    })
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Saved tests for two users and guest results, sharing every sort value with others.
+    fn filled() -> DB {
+        let mut db = DB::new(Path::new(":memory:")).unwrap();
+        let mut test = TypingTest::new();
+        for i in 0..24 {
+            test.wpm = 40 + i % 3;
+            test.raw_wpm = 50 + i % 2;
+            test.accuracy = 90 + i % 4;
+            match i % 3 {
+                0 => {
+                    db.add_guest_test(&test).unwrap();
+                }
+                n => {
+                    let user = db.login(["ann", "bob"][n as usize - 1]).unwrap();
+                    db.add_test(user.id, &test).unwrap();
+                }
+            }
+        }
+        db.conn
+            .execute_batch(
+                "UPDATE tests SET created_at = 1000 + id % 3;
+                 UPDATE temp.guest_tests SET created_at = 1000 + id % 3;
+                 -- from before dates and raw speed were recorded
+                 UPDATE tests SET created_at = NULL, raw_wpm = NULL WHERE id % 4 = 0;",
+            )
+            .unwrap();
+        db
+    }
+
+    fn row_ids(rows: &[TestRow]) -> Vec<i64> {
+        rows.iter().map(|row| row.row_id).collect()
+    }
+
+    #[test]
+    fn history_pages_follow_one_order() {
+        let db = filled();
+        for sort in SortColumn::ALL {
+            for descending in [true, false] {
+                let filter = HistoryFilter {
+                    sort,
+                    descending,
+                    ..HistoryFilter::default()
+                };
+                assert_eq!(db.history_count(&filter).unwrap(), 24);
+                let mut rows = db.history_page(&filter, HistoryPage::First, 100).unwrap();
+                rows.sort_by_key(|row| {
+                    let key = row.key(sort);
+                    (key.value, key.row_id)
+                });
+                if descending {
+                    rows.reverse();
+                }
+                let expected = row_ids(&rows);
+
+                let mut forwards = Vec::new();
+                let mut page = HistoryPage::First;
+                loop {
+                    let rows = db.history_page(&filter, page, 5).unwrap();
+                    let Some(last) = rows.last() else {
+                        break;
+                    };
+                    page = HistoryPage::After(last.key(sort));
+                    forwards.extend(row_ids(&rows));
+                }
+                assert_eq!(forwards, expected, "{:?} descending {}", sort, descending);
+
+                let mut backwards = Vec::new();
+                let mut page = HistoryPage::Last;
+                loop {
+                    let rows = db.history_page(&filter, page, 5).unwrap();
+                    let Some(first) = rows.first() else {
+                        break;
+                    };
+                    page = HistoryPage::Before(first.key(sort));
+                    backwards.splice(0..0, row_ids(&rows));
+                }
+                assert_eq!(backwards, expected, "{:?} descending {}", sort, descending);
+            }
         }
-        Ok(results)
     }
 }
@@ -0,0 +1,190 @@
+use crate::code::Language;
+
+/// Coarse token classes, enough to colour code snippets on the typing screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Token {
+    Plain,
+    Keyword,
+    String,
+    Comment,
+    Number,
+}
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "false",
+    "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "type", "unsafe",
+    "use", "where", "while",
+];
+const PYTHON_KEYWORDS: &[&str] = &[
+    "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del", "elif",
+    "else", "except", "False", "finally", "for", "from", "global", "if", "import", "in", "is",
+    "lambda", "None", "nonlocal", "not", "or", "pass", "raise", "return", "self", "True", "try",
+    "while", "with", "yield",
+];
+const JS_KEYWORDS: &[&str] = &[
+    "async",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "constructor",
+    "continue",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "import",
+    "in",
+    "instanceof",
+    "let",
+    "new",
+    "null",
+    "of",
+    "return",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "undefined",
+    "var",
+    "void",
+    "while",
+    "yield",
+];
+const GO_KEYWORDS: &[&str] = &[
+    "break",
+    "case",
+    "chan",
+    "const",
+    "continue",
+    "default",
+    "defer",
+    "else",
+    "false",
+    "for",
+    "func",
+    "go",
+    "goto",
+    "if",
+    "import",
+    "interface",
+    "map",
+    "nil",
+    "package",
+    "range",
+    "return",
+    "select",
+    "struct",
+    "switch",
+    "true",
+    "type",
+    "var",
+];
+const C_KEYWORDS: &[&str] = &[
+    "break", "case", "char", "class", "const", "continue", "default", "do", "double", "else",
+    "enum", "extern", "false", "float", "for", "if", "int", "long", "new", "private", "public",
+    "return", "short", "static", "struct", "switch", "this", "true", "typedef", "unsigned", "void",
+    "while",
+];
+
+fn keywords(language: Language) -> &'static [&'static str] {
+    match language {
+        Language::Rust => RUST_KEYWORDS,
+        Language::Python => PYTHON_KEYWORDS,
+        Language::JavaScript => JS_KEYWORDS,
+        Language::Go => GO_KEYWORDS,
+        Language::C => C_KEYWORDS,
+        Language::Other => &[],
+    }
+}
+
+fn line_comment(language: Language) -> &'static str {
+    match language {
+        Language::Python => "#",
+        _ => "//",
+    }
+}
+
+/// Classifies every char of `text`, the result has one entry per `char`.
+pub fn highlight(text: &str, language: Language) -> Vec<Token> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = vec![Token::Plain; chars.len()];
+    let keywords = keywords(language);
+    let comment: Vec<char> = line_comment(language).chars().collect();
+    let block_comments = !matches!(language, Language::Python | Language::Other);
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        let kind = if chars[i..].starts_with(&comment) {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            Token::Comment
+        } else if block_comments && chars[i..].starts_with(&['/', '*']) {
+            i += 2;
+            while i < chars.len() && !chars[i - 1..].starts_with(&['*', '/']) {
+                i += 1;
+            }
+            i = (i + 1).min(chars.len());
+            Token::Comment
+        } else if is_quote(&chars, i, language) {
+            i += 1;
+            while i < chars.len() && chars[i] != c && chars[i] != '\n' {
+                if chars[i] == '\\' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            i = (i + 1).min(chars.len());
+            Token::String
+        } else if c.is_ascii_digit() {
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '.') {
+                i += 1;
+            }
+            Token::Number
+        } else if c.is_alphabetic() || c == '_' {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            if keywords.contains(&word.as_str()) {
+                Token::Keyword
+            } else {
+                Token::Plain
+            }
+        } else {
+            i += 1;
+            Token::Plain
+        };
+        tokens[start..i].fill(kind);
+    }
+    tokens
+}
+
+// rust uses ' for lifetimes as well as chars, only treat it as a quote around a single char
+fn is_quote(chars: &[char], i: usize, language: Language) -> bool {
+    match chars[i] {
+        '"' | '`' => true,
+        '\'' if language == Language::Rust => {
+            chars.get(i + 2) == Some(&'\'') || chars.get(i + 1) == Some(&'\\')
+        }
+        '\'' => language != Language::Other,
+        _ => false,
+    }
+}
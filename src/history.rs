@@ -0,0 +1,148 @@
+/// Test kinds as they start a mode key, e.g. "time" in "time 30 · english 20k · full".
+pub const MODES: [&str; 5] = ["time", "words", "quote", "code", "custom"];
+
+/// Whose results the History screen lists.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UserScope {
+    All,
+    User {
+        id: i64,
+        username: String,
+    },
+    /// Results typed without logging in this session.
+    Guest,
+}
+
+impl UserScope {
+    pub fn label(&self) -> &str {
+        match self {
+            UserScope::All => "everyone",
+            UserScope::User { username, .. } => username,
+            UserScope::Guest => "guest",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DateRange {
+    All,
+    Today,
+    Week,
+    Month,
+}
+
+impl DateRange {
+    pub const ALL: [DateRange; 4] = [
+        DateRange::All,
+        DateRange::Today,
+        DateRange::Week,
+        DateRange::Month,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            DateRange::All => "all time",
+            DateRange::Today => "today",
+            DateRange::Week => "last 7 days",
+            DateRange::Month => "last 30 days",
+        }
+    }
+
+    /// sqlite `strftime` arguments for the earliest moment in the range.
+    pub fn since_sql(&self) -> Option<&'static str> {
+        match self {
+            DateRange::All => None,
+            // local midnight, converted back to UTC to compare with unix times
+            DateRange::Today => Some("'now', 'localtime', 'start of day', 'utc'"),
+            DateRange::Week => Some("'now', '-7 days'"),
+            DateRange::Month => Some("'now', '-30 days'"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortColumn {
+    Date,
+    Wpm,
+    RawWpm,
+    Accuracy,
+}
+
+impl SortColumn {
+    pub const ALL: [SortColumn; 4] = [
+        SortColumn::Date,
+        SortColumn::Wpm,
+        SortColumn::RawWpm,
+        SortColumn::Accuracy,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SortColumn::Date => "date",
+            SortColumn::Wpm => "wpm",
+            SortColumn::RawWpm => "raw",
+            SortColumn::Accuracy => "accuracy",
+        }
+    }
+
+    pub fn column(&self) -> &'static str {
+        match self {
+            SortColumn::Date => "created_at",
+            SortColumn::Wpm => "wpm",
+            SortColumn::RawWpm => "raw_wpm",
+            SortColumn::Accuracy => "accuracy",
+        }
+    }
+}
+
+/// Which results the History screen shows and in what order, applied in the query.
+#[derive(Clone, Debug)]
+pub struct HistoryFilter {
+    pub user: UserScope,
+    /// One of `MODES`.
+    pub mode: Option<&'static str>,
+    /// A mode with its length, e.g. "time 30" or "quote short", only set along with `mode`.
+    pub length: Option<String>,
+    pub dates: DateRange,
+    pub sort: SortColumn,
+    pub descending: bool,
+}
+
+impl Default for HistoryFilter {
+    fn default() -> Self {
+        HistoryFilter {
+            user: UserScope::All,
+            mode: None,
+            length: None,
+            dates: DateRange::All,
+            sort: SortColumn::Wpm,
+            descending: true,
+        }
+    }
+}
+
+/// The test kind at the start of a mode key, "time 30 · english 20k · full" gives "time 30".
+pub fn test_kind(mode_key: &str) -> &str {
+    mode_key.split(" · ").next().unwrap_or(mode_key)
+}
+
+/// Rows fetched at a time on the History screen.
+pub const PAGE_SIZE: usize = 50;
+
+/// A row's place in the sort order: the sorted column, then the row itself to break ties.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HistoryKey {
+    pub value: i64,
+    pub row_id: i64,
+}
+
+/// Which page of History to fetch, relative to the rows on screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HistoryPage {
+    First,
+    Last,
+    /// The rows following this one.
+    After(HistoryKey),
+    /// The rows leading up to this one.
+    Before(HistoryKey),
+}
@@ -1,24 +1,113 @@
 pub mod app;
+pub mod auth;
+pub mod cli;
+pub mod code;
+pub mod custom;
 pub mod db;
 pub mod event;
+pub mod highlight;
+pub mod history;
+pub mod metrics;
+pub mod migrations;
+pub mod paths;
+pub mod quotes;
 pub mod typingtest;
 pub mod ui;
+pub mod words;
 
+use cli::{Args, TextArg};
+use code::SnippetBook;
+use custom::CustomText;
+use db::DB;
 use event::AppEventHandler;
 #[allow(dead_code)]
 use ratatui::prelude::CrosstermBackend;
-use std::{error::Error, io};
+use std::{
+    error::Error,
+    fs,
+    io::{self, BufRead, IsTerminal, Write},
+    path::Path,
+};
 use ui::UI;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    // stdin nobody asked for, like /dev/null from a launcher or cron, only counts if it has text
+    let (text, detected_stdin) = match args.text {
+        Some(arg) => (Some(arg), false),
+        None if !io::stdin().is_terminal() => (Some(TextArg::Stdin), true),
+        None => (None, false),
+    };
+    let custom = text.and_then(|arg| {
+        let loaded = match &arg {
+            TextArg::File(path) => CustomText::from_file(path),
+            TextArg::Stdin => CustomText::from_stdin(),
+        };
+        match loaded {
+            Ok(custom) => Some(custom),
+            Err(e) if detected_stdin && e.kind() == io::ErrorKind::InvalidData => None,
+            Err(e) => {
+                eprintln!("Error reading practice text: {}", e);
+                std::process::exit(1);
+            }
+        }
+    });
+
+    let db_path = paths::db_path(args.db_path.as_deref());
+    offer_legacy_import(&db_path);
+
+    // open the database before touching the terminal so errors are readable
+    let db = match DB::new(&db_path) {
+        Ok(db) => db,
+        Err(e) => {
+            eprintln!("Error opening or creating database: {}", e);
+            std::process::exit(1);
+        }
+    };
+
     let stdout = io::stdout();
     let backend = CrosstermBackend::new(stdout);
     let terminal = ratatui::Terminal::new(backend)?;
 
-    let app = app::TypeTui::new();
+    let mut app = app::TypeTui::new(db);
+    if !args.code_paths.is_empty() {
+        app.test_opts.snippets = SnippetBook::load(&args.code_paths);
+    }
+    if let Some(custom) = custom {
+        app.start_custom(custom);
+    }
     let mut ui = UI::new(terminal, AppEventHandler::new(100), app);
     ui.init().await?;
 
     Ok(())
 }
+
+/// On first run with the new location, offers to bring over a `./typetui.db` left behind by
+/// older builds. Migrations upgrade the copy when it is opened.
+fn offer_legacy_import(db_path: &Path) {
+    let legacy = paths::legacy_db_path();
+    if db_path.exists() || !legacy.is_file() || !io::stdin().is_terminal() {
+        return;
+    }
+    print!(
+        "Found an existing {} in this directory. Import it into {}? [Y/n] ",
+        legacy.display(),
+        db_path.display()
+    );
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    if io::stdin().lock().read_line(&mut answer).is_err() {
+        return;
+    }
+    if matches!(answer.trim(), "" | "y" | "Y" | "yes") {
+        let copied = db_path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::copy(&legacy, db_path));
+        match copied {
+            Ok(_) => println!("Imported, the old file was left in place."),
+            Err(e) => eprintln!("Could not import {}: {}", legacy.display(), e),
+        }
+    }
+}
@@ -0,0 +1,357 @@
+use std::time::Duration;
+
+use crate::typingtest::{split_words, KeystrokeKind, TypingTest};
+
+/// MonkeyType style numbers worked out from a finished test's input and keystroke log.
+#[derive(Clone, Debug, Default)]
+pub struct Metrics {
+    pub samples: Vec<Sample>,
+    /// 0-100, how even the per-second raw speed was.
+    pub consistency: i32,
+    pub words: Vec<WordBurst>,
+    pub chars: CharCounts,
+}
+
+/// One second of the test.
+#[derive(Clone, Copy, Debug)]
+pub struct Sample {
+    /// 1-based, the sample covers the second ending here.
+    pub second: u32,
+    /// Speed counting the correct keys pressed so far.
+    pub wpm: i32,
+    /// Speed counting every key pressed within this second alone.
+    pub raw_wpm: i32,
+    pub errors: i32,
+}
+
+/// How fast a single word was typed, from finishing the previous word to finishing this one.
+#[derive(Clone, Debug)]
+pub struct WordBurst {
+    pub word: String,
+    pub typed: String,
+    pub burst_wpm: i32,
+}
+
+/// Letters compared within their word: `extra` were typed past its end, `missed` were skipped
+/// by moving on early.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CharCounts {
+    pub correct: i32,
+    pub incorrect: i32,
+    pub extra: i32,
+    pub missed: i32,
+}
+
+impl Metrics {
+    pub fn compute(test: &TypingTest) -> Metrics {
+        let duration = test.time.unwrap_or_else(|| test.elapsed());
+        let raw = raw_per_second(test, duration);
+        Metrics {
+            samples: samples(test, duration, &raw),
+            consistency: consistency(&raw),
+            words: word_bursts(test),
+            chars: char_counts(test),
+        }
+    }
+}
+
+/// Raw speed for every second, the last partial second scaled up to a full one.
+fn raw_per_second(test: &TypingTest, duration: Duration) -> Vec<f64> {
+    let secs = duration.as_secs_f64();
+    let count = secs.ceil() as usize;
+    let mut typed = vec![0usize; count];
+    for key in &test.keystrokes {
+        if key.kind != KeystrokeKind::Backspace {
+            if let Some(slot) = typed.get_mut(bucket(key.offset, count)) {
+                *slot += 1;
+            }
+        }
+    }
+    typed
+        .iter()
+        .enumerate()
+        .map(|(i, n)| {
+            let length = (secs - i as f64).min(1.0);
+            *n as f64 / 5.0 * 60.0 / length
+        })
+        .collect()
+}
+
+fn samples(test: &TypingTest, duration: Duration, raw: &[f64]) -> Vec<Sample> {
+    let secs = duration.as_secs_f64();
+    let mut correct = vec![0usize; raw.len()];
+    let mut errors = vec![0i32; raw.len()];
+    for key in &test.keystrokes {
+        let i = bucket(key.offset, raw.len());
+        match key.kind {
+            KeystrokeKind::Correct if i < raw.len() => correct[i] += 1,
+            KeystrokeKind::Incorrect if i < raw.len() => errors[i] += 1,
+            _ => {}
+        }
+    }
+    let mut so_far = 0;
+    raw.iter()
+        .enumerate()
+        .map(|(i, raw_wpm)| {
+            so_far += correct[i];
+            let mins = (i as f64 + 1.0).min(secs) / 60.0;
+            Sample {
+                second: i as u32 + 1,
+                wpm: (so_far as f64 / 5.0 / mins).round() as i32,
+                raw_wpm: raw_wpm.round() as i32,
+                errors: errors[i],
+            }
+        })
+        .collect()
+}
+
+/// Second a key press falls in, presses after the end go in the last one.
+fn bucket(offset: Duration, count: usize) -> usize {
+    (offset.as_secs() as usize).min(count.saturating_sub(1))
+}
+
+/// MonkeyType's consistency: the coefficient of variation of the raw speed, squashed into
+/// 0-100 so that perfectly even typing scores 100.
+fn consistency(raw: &[f64]) -> i32 {
+    if raw.is_empty() {
+        return 0;
+    }
+    let mean = raw.iter().sum::<f64>() / raw.len() as f64;
+    if mean <= 0.0 {
+        return 0;
+    }
+    let variance = raw.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / raw.len() as f64;
+    let cv = variance.sqrt() / mean;
+    let squashed = (cv + cv.powi(3) / 3.0 + cv.powi(5) / 5.0).tanh();
+    (100.0 * (1.0 - squashed)).round() as i32
+}
+
+fn word_bursts(test: &TypingTest) -> Vec<WordBurst> {
+    let words = split_words(&test.test_text);
+    let typed = split_words(&test.user_input);
+
+    // when each word was last finished, backspacing over a separator reopens the word
+    let mut ends: Vec<Option<Duration>> = vec![None; typed.len()];
+    let mut index: usize = 0;
+    // word index at each newline, auto-indentation after one is added without a key press
+    let mut newlines = Vec::new();
+    for key in &test.keystrokes {
+        let separator = key.typed.is_whitespace();
+        match key.kind {
+            KeystrokeKind::Backspace if key.typed == '\n' => index = newlines.pop().unwrap_or(0),
+            KeystrokeKind::Backspace if separator => index = index.saturating_sub(1),
+            KeystrokeKind::Correct | KeystrokeKind::Incorrect if separator => {
+                if let Some(end) = ends.get_mut(index) {
+                    *end = Some(key.offset);
+                }
+                if key.typed == '\n' {
+                    newlines.push(index);
+                    index += 1;
+                    while words
+                        .get(index)
+                        .is_some_and(|(word, sep)| word.is_empty() && *sep == Some(' '))
+                    {
+                        index += 1;
+                    }
+                } else {
+                    index += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    if let (Some(end), Some(last)) = (ends.last_mut(), test.keystrokes.last()) {
+        end.get_or_insert(last.offset);
+    }
+
+    let mut start = Duration::ZERO;
+    let mut bursts = Vec::new();
+    for (i, ((typed, _), (word, _))) in typed.iter().zip(&words).enumerate() {
+        let end = ends[i].unwrap_or(start);
+        let mins = end.saturating_sub(start).as_secs_f64() / 60.0;
+        start = end;
+        // indentation in code has nothing to time
+        if word.is_empty() || typed.is_empty() {
+            continue;
+        }
+        let burst_wpm = if mins > 0.0 {
+            (typed.chars().count() as f64 / 5.0 / mins).round() as i32
+        } else {
+            0
+        };
+        bursts.push(WordBurst {
+            word: word.to_string(),
+            typed: typed.to_string(),
+            burst_wpm,
+        });
+    }
+    bursts
+}
+
+fn char_counts(test: &TypingTest) -> CharCounts {
+    let words = split_words(&test.test_text);
+    let typed = split_words(&test.user_input);
+    let mut counts = CharCounts::default();
+    for ((typed, sep), (word, _)) in typed.iter().zip(&words) {
+        let typed: Vec<char> = typed.chars().collect();
+        let word: Vec<char> = word.chars().collect();
+        for (t, w) in typed.iter().zip(&word) {
+            if t == w {
+                counts.correct += 1;
+            } else {
+                counts.incorrect += 1;
+            }
+        }
+        counts.extra += typed.len().saturating_sub(word.len()) as i32;
+        // the word being typed when the test ended hasn't been skipped
+        if sep.is_some() {
+            counts.missed += word.len().saturating_sub(typed.len()) as i32;
+        }
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::typingtest::Keystroke;
+    use KeystrokeKind::{Backspace, Correct, Incorrect};
+
+    /// A finished test, `keys` are (char, seconds on the clock, kind).
+    fn test(text: &str, input: &str, secs: f64, keys: &[(char, f64, KeystrokeKind)]) -> TypingTest {
+        let mut test = TypingTest::new();
+        test.test_text = text.to_string();
+        test.user_input = input.to_string();
+        test.time = Some(Duration::from_secs_f64(secs));
+        test.keystrokes = keys
+            .iter()
+            .map(|&(typed, at, kind)| Keystroke {
+                typed,
+                expected: None,
+                offset: Duration::from_secs_f64(at),
+                kind,
+            })
+            .collect();
+        test
+    }
+
+    #[test]
+    fn consistency() {
+        let cases: [(&[f64], i32); 5] = [
+            (&[], 0),
+            (&[0.0, 0.0], 0),
+            (&[60.0, 60.0, 60.0], 100),
+            (&[50.0, 70.0], 83),
+            (&[10.0, 110.0], 20),
+        ];
+        for (raw, expected) in cases {
+            assert_eq!(super::consistency(raw), expected, "{:?}", raw);
+        }
+    }
+
+    #[test]
+    fn bucket() {
+        let cases = [
+            // (seconds, buckets, bucket)
+            (0.5, 3, 0),
+            (1.0, 3, 1),
+            // presses after the end go in the last second
+            (5.0, 3, 2),
+            (0.5, 0, 0),
+        ];
+        for (secs, count, expected) in cases {
+            assert_eq!(
+                super::bucket(Duration::from_secs_f64(secs), count),
+                expected,
+                "{}s",
+                secs
+            );
+        }
+    }
+
+    #[test]
+    fn last_partial_second_is_scaled_up() {
+        let keys = [
+            ('a', 0.1, Correct),
+            ('b', 0.2, Incorrect),
+            ('b', 0.3, Backspace),
+            ('c', 0.4, Correct),
+            ('d', 0.5, Correct),
+            ('e', 0.6, Correct),
+            ('f', 1.1, Correct),
+        ];
+        let test = test("acdef f", "acdef f", 1.5, &keys);
+        // 5 presses in the first second, 1 in the half second after it
+        let raw = raw_per_second(&test, Duration::from_secs_f64(1.5));
+        assert_eq!(raw, [60.0, 24.0]);
+
+        let samples = samples(&test, Duration::from_secs_f64(1.5), &raw);
+        let samples: Vec<(u32, i32, i32, i32)> = samples
+            .iter()
+            .map(|s| (s.second, s.wpm, s.raw_wpm, s.errors))
+            .collect();
+        assert_eq!(samples, [(1, 48, 60, 1), (2, 40, 24, 0)]);
+    }
+
+    #[test]
+    fn char_counts() {
+        let cases = [
+            // (text, input, (correct, incorrect, extra, missed))
+            ("the cat", "the cat", (6, 0, 0, 0)),
+            ("the cat", "thx cat", (5, 1, 0, 0)),
+            ("the cat", "thee cat", (6, 0, 1, 0)),
+            // skipped letters are missed, the word still being typed isn't
+            ("the cat", "th c", (3, 0, 0, 1)),
+            ("a\n  b", "a\n  b", (2, 0, 0, 0)),
+        ];
+        for (text, input, expected) in cases {
+            let counts = super::char_counts(&test(text, input, 1.0, &[]));
+            assert_eq!(
+                (
+                    counts.correct,
+                    counts.incorrect,
+                    counts.extra,
+                    counts.missed
+                ),
+                expected,
+                "{:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn word_bursts_skip_indentation() {
+        // Enter fills in the indentation after the newline without any key presses
+        let keys = [
+            ('a', 0.2, Correct),
+            ('\n', 1.0, Correct),
+            ('b', 1.3, Correct),
+            ('c', 1.6, Correct),
+        ];
+        let bursts = word_bursts(&test("a\n  bc", "a\n  bc", 1.6, &keys));
+        let bursts: Vec<(&str, i32)> = bursts
+            .iter()
+            .map(|b| (b.word.as_str(), b.burst_wpm))
+            .collect();
+        assert_eq!(bursts, [("a", 12), ("bc", 40)]);
+    }
+
+    #[test]
+    fn word_bursts_after_backspacing_a_separator() {
+        let keys = [
+            ('a', 0.5, Correct),
+            (' ', 1.0, Correct),
+            (' ', 1.5, Backspace),
+            (' ', 3.0, Correct),
+            ('b', 4.0, Correct),
+        ];
+        let bursts = word_bursts(&test("a b", "a b", 4.0, &keys));
+        let bursts: Vec<(&str, i32)> = bursts
+            .iter()
+            .map(|b| (b.word.as_str(), b.burst_wpm))
+            .collect();
+        // the first word ends when it was left the second time
+        assert_eq!(bursts, [("a", 4), ("b", 12)]);
+    }
+}
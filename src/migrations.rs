@@ -0,0 +1,398 @@
+use rusqlite::{Connection, Result, Transaction};
+
+use crate::db::DbError;
+
+type Migration = fn(&Transaction) -> Result<()>;
+
+/// Ordered schema upgrades, `MIGRATIONS[i]` takes a database from version `i` to `i + 1`.
+/// Only ever append to this list, shipped steps must never change.
+const MIGRATIONS: &[Migration] = &[
+    v1_initial_tables,
+    v2_test_metadata,
+    v3_test_seed,
+    v4_word_band,
+    v5_punctuation_numbers,
+    v6_quote_id,
+    v7_custom_progress,
+    v8_test_metrics,
+    v9_mode_key,
+    v10_personal_bests,
+    v11_user_ids,
+    v12_user_passphrase,
+    v13_history_indexes,
+    v14_history_sort_indexes,
+];
+
+/// The schema version this build writes.
+pub const SCHEMA_VERSION: i64 = MIGRATIONS.len() as i64;
+
+/// Brings the database up to `SCHEMA_VERSION`, all pending steps run in a single transaction
+/// so a failed upgrade leaves the file exactly as it was.
+pub fn run(conn: &mut Connection) -> std::result::Result<(), DbError> {
+    let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if version > SCHEMA_VERSION {
+        return Err(DbError::NewerSchema {
+            found: version,
+            supported: SCHEMA_VERSION,
+        });
+    }
+    if version == SCHEMA_VERSION {
+        return Ok(());
+    }
+
+    let tx = conn.transaction()?;
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(&tx)?;
+    }
+    tx.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+    tx.commit()?;
+    Ok(())
+}
+
+fn has_column(tx: &Transaction, table: &str, column: &str) -> Result<bool> {
+    tx.query_row(
+        "SELECT count(*) FROM pragma_table_info(?1) WHERE name = ?2",
+        [table, column],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|n| n > 0)
+}
+
+/// `ALTER TABLE .. ADD COLUMN` that is a no-op when the column already exists.
+fn add_column(tx: &Transaction, table: &str, column: &str, decl: &str) -> Result<()> {
+    if !has_column(tx, table, column)? {
+        tx.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl),
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+// the original schema, unversioned databases already have these tables
+fn v1_initial_tables(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE if not exists users (
+        id integer primary key,
+        username text not null unique,
+        total_tests integer,
+        top_wpm integer);
+
+        CREATE TABLE if not exists tests (
+        id integer primary key,
+        username text not null ,
+        wpm integer not null ,
+        raw_wpm integer,
+        accuracy integer not null,
+        word_count integer not null,
+        time integer not null);",
+    )
+}
+
+fn v2_test_metadata(tx: &Transaction) -> Result<()> {
+    add_column(tx, "tests", "created_at", "integer")?;
+    add_column(tx, "tests", "mode", "text")?;
+    add_column(tx, "tests", "test_text", "text")?;
+    add_column(tx, "tests", "user_input", "text")?;
+    tx.execute_batch(
+        "CREATE TABLE if not exists keystrokes (
+        id integer primary key,
+        test_id integer not null references tests(id) on delete cascade,
+        seq integer not null,
+        typed text not null,
+        expected text,
+        offset_ms integer not null,
+        kind text not null);",
+    )
+}
+
+fn v3_test_seed(tx: &Transaction) -> Result<()> {
+    add_column(tx, "tests", "seed", "integer")?;
+    add_column(tx, "tests", "word_list", "text")
+}
+
+// rows from before bands existed sampled the whole list
+fn v4_word_band(tx: &Transaction) -> Result<()> {
+    add_column(tx, "tests", "band", "text not null default 'full'")
+}
+
+fn v5_punctuation_numbers(tx: &Transaction) -> Result<()> {
+    add_column(tx, "tests", "punctuation", "integer not null default 0")?;
+    add_column(tx, "tests", "numbers", "integer not null default 0")
+}
+
+fn v6_quote_id(tx: &Transaction) -> Result<()> {
+    add_column(tx, "tests", "quote_id", "text")?;
+    tx.execute_batch("CREATE INDEX if not exists tests_quote_id ON tests (quote_id)")
+}
+
+fn v7_custom_progress(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE if not exists custom_progress (
+        hash text primary key,
+        name text not null,
+        section integer not null,
+        updated_at integer not null);",
+    )
+}
+
+fn v8_test_metrics(tx: &Transaction) -> Result<()> {
+    add_column(tx, "tests", "consistency", "integer")?;
+    add_column(tx, "tests", "correct_chars", "integer")?;
+    add_column(tx, "tests", "incorrect_chars", "integer")?;
+    add_column(tx, "tests", "extra_chars", "integer")?;
+    add_column(tx, "tests", "missed_chars", "integer")?;
+    tx.execute_batch(
+        "CREATE TABLE if not exists test_samples (
+        test_id integer not null references tests(id) on delete cascade,
+        second integer not null,
+        wpm integer not null,
+        raw_wpm integer not null,
+        errors integer not null,
+        primary key (test_id, second));
+
+        CREATE TABLE if not exists test_words (
+        test_id integer not null references tests(id) on delete cascade,
+        seq integer not null,
+        word text not null,
+        typed text not null,
+        burst_wpm integer not null,
+        primary key (test_id, seq));",
+    )
+}
+
+// older rows are left without a key, they never count as a personal best
+fn v9_mode_key(tx: &Transaction) -> Result<()> {
+    add_column(tx, "tests", "mode_key", "text")?;
+    tx.execute_batch("CREATE INDEX if not exists tests_user_mode_key ON tests (username, mode_key)")
+}
+
+fn v10_personal_bests(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE if not exists personal_bests (
+        username text not null,
+        mode_key text not null,
+        wpm integer not null,
+        accuracy integer not null,
+        test_id integer references tests(id) on delete set null,
+        achieved_at integer,
+        primary key (username, mode_key));
+
+        INSERT OR IGNORE INTO personal_bests (username, mode_key, wpm, accuracy, test_id, achieved_at)
+        SELECT username, mode_key, wpm, accuracy, id, created_at FROM tests t
+         WHERE mode_key IS NOT NULL
+           AND id = (SELECT id FROM tests b
+                      WHERE b.username = t.username AND b.mode_key = t.mode_key
+                      ORDER BY wpm DESC, id LIMIT 1);",
+    )
+}
+
+// tests point at a users row instead of repeating the name, users are created from the names
+// already in the history and their totals filled in from it
+fn v11_user_ids(tx: &Transaction) -> Result<()> {
+    add_column(tx, "users", "created_at", "integer")?;
+    add_column(tx, "users", "last_login_at", "integer")?;
+    add_column(tx, "users", "last_test_at", "integer")?;
+    add_column(tx, "tests", "user_id", "integer references users(id)")?;
+    tx.execute_batch(
+        "INSERT OR IGNORE INTO users (username) SELECT DISTINCT username FROM tests;
+        UPDATE tests SET user_id = (SELECT id FROM users WHERE users.username = tests.username);
+        UPDATE users SET
+            total_tests = (SELECT count(*) FROM tests WHERE user_id = users.id),
+            top_wpm = (SELECT max(wpm) FROM tests WHERE user_id = users.id),
+            last_test_at = (SELECT max(created_at) FROM tests WHERE user_id = users.id);
+
+        DROP INDEX if exists tests_user_mode_key;
+        ALTER TABLE tests DROP COLUMN username;
+        CREATE INDEX tests_user_mode_key ON tests (user_id, mode_key);
+
+        CREATE TABLE personal_bests_new (
+        user_id integer not null references users(id) on delete cascade,
+        mode_key text not null,
+        wpm integer not null,
+        accuracy integer not null,
+        test_id integer references tests(id) on delete set null,
+        achieved_at integer,
+        primary key (user_id, mode_key));
+        INSERT INTO personal_bests_new
+        SELECT users.id, mode_key, wpm, accuracy, test_id, achieved_at
+          FROM personal_bests JOIN users ON users.username = personal_bests.username;
+        DROP TABLE personal_bests;
+        ALTER TABLE personal_bests_new RENAME TO personal_bests;
+
+        CREATE TABLE if not exists settings (
+        key text primary key,
+        value text not null);",
+    )
+}
+
+fn v12_user_passphrase(tx: &Transaction) -> Result<()> {
+    add_column(tx, "users", "passphrase_hash", "text")?;
+    add_column(tx, "users", "failed_logins", "integer not null default 0")?;
+    add_column(tx, "users", "locked_until", "integer")
+}
+
+// History pages are read off these in order, the date one is on the same expression the
+// query sorts by so tests from before dates were recorded don't need a separate pass
+fn v13_history_indexes(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(
+        "CREATE INDEX if not exists tests_user_created_at ON tests (user_id, coalesce(created_at, 0));
+        CREATE INDEX if not exists tests_user_wpm ON tests (user_id, wpm);
+        CREATE INDEX if not exists tests_wpm ON tests (wpm);",
+    )
+}
+
+// The rest of History's orders, each for one user and for everyone, so no sort falls back
+// to reading every test and sorting them
+fn v14_history_sort_indexes(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(
+        "CREATE INDEX if not exists tests_created_at ON tests (coalesce(created_at, 0));
+        CREATE INDEX if not exists tests_user_raw_wpm ON tests (user_id, coalesce(raw_wpm, 0));
+        CREATE INDEX if not exists tests_raw_wpm ON tests (coalesce(raw_wpm, 0));
+        CREATE INDEX if not exists tests_user_accuracy ON tests (user_id, accuracy);
+        CREATE INDEX if not exists tests_accuracy ON tests (accuracy);",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The tables typetui created before the schema was versioned.
+    fn baseline() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE users (
+            id integer primary key,
+            username text not null unique,
+            total_tests integer,
+            top_wpm integer);
+
+            CREATE TABLE tests (
+            id integer primary key,
+            username text not null ,
+            wpm integer not null ,
+            raw_wpm integer,
+            accuracy integer not null,
+            word_count integer not null,
+            time integer not null);
+
+            INSERT INTO tests (username, wpm, raw_wpm, accuracy, word_count, time) values
+                ('ann', 70, 75, 95, 10, 15),
+                ('ann', 82, 90, 97, 10, 30),
+                ('bob', 55, 60, 90, 25, 0);",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn version(conn: &Connection) -> i64 {
+        conn.pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn baseline_history_survives() {
+        let mut conn = baseline();
+        run(&mut conn).unwrap();
+        assert_eq!(version(&conn), SCHEMA_VERSION);
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT users.username, wpm, raw_wpm, accuracy, word_count, time
+                   FROM tests JOIN users ON users.id = tests.user_id ORDER BY tests.id",
+            )
+            .unwrap();
+        let tests: Vec<(String, i32, i32, i32, i32, i32)> = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                ))
+            })
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(
+            tests,
+            [
+                ("ann".to_string(), 70, 75, 95, 10, 15),
+                ("ann".to_string(), 82, 90, 97, 10, 30),
+                ("bob".to_string(), 55, 60, 90, 25, 0),
+            ]
+        );
+
+        let mut stmt = conn
+            .prepare("SELECT username, total_tests, top_wpm FROM users ORDER BY username")
+            .unwrap();
+        let users: Vec<(String, i32, i32)> = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(
+            users,
+            [("ann".to_string(), 2, 82), ("bob".to_string(), 1, 55)]
+        );
+    }
+
+    #[test]
+    fn personal_bests_move_to_user_ids() {
+        let mut conn = baseline();
+        {
+            let tx = conn.transaction().unwrap();
+            for migration in &MIGRATIONS[..10] {
+                migration(&tx).unwrap();
+            }
+            tx.execute_batch(
+                "INSERT INTO tests (username, wpm, accuracy, word_count, time, mode_key)
+                     values ('bob', 64, 98, 10, 30, 'time 30');
+                 INSERT INTO personal_bests (username, mode_key, wpm, accuracy, test_id)
+                     values ('bob', 'time 30', 64, 98, last_insert_rowid());
+                 PRAGMA user_version = 10;",
+            )
+            .unwrap();
+            tx.commit().unwrap();
+        }
+        run(&mut conn).unwrap();
+
+        let best: (String, String, i32) = conn
+            .query_row(
+                "SELECT users.username, mode_key, wpm
+                   FROM personal_bests JOIN users ON users.id = personal_bests.user_id",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(best, ("bob".to_string(), "time 30".to_string(), 64));
+    }
+
+    #[test]
+    fn up_to_date_database_is_left_alone() {
+        let mut conn = baseline();
+        run(&mut conn).unwrap();
+        run(&mut conn).unwrap();
+        assert_eq!(version(&conn), SCHEMA_VERSION);
+        let count: i64 = conn
+            .query_row("SELECT count(*) FROM tests", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn newer_schema_is_refused() {
+        let mut conn = baseline();
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION + 1)
+            .unwrap();
+        assert!(matches!(
+            run(&mut conn),
+            Err(DbError::NewerSchema { found, supported })
+                if found == SCHEMA_VERSION + 1 && supported == SCHEMA_VERSION
+        ));
+        assert!(!has_column(&conn.transaction().unwrap(), "tests", "user_id").unwrap());
+    }
+}
@@ -0,0 +1,51 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+/// Environment variable that overrides where the database lives.
+pub const DB_ENV_VAR: &str = "TYPETUI_DB";
+
+const APP_DIR: &str = "typetui";
+const DB_FILE: &str = "typetui.db";
+
+/// Older builds created the database in whatever directory they were launched from.
+pub fn legacy_db_path() -> PathBuf {
+    PathBuf::from(DB_FILE)
+}
+
+/// `$XDG_DATA_HOME/typetui`, falling back to `~/.local/share/typetui`.
+pub fn data_dir() -> PathBuf {
+    xdg_dir("XDG_DATA_HOME", &[".local", "share"])
+}
+
+/// `$XDG_CONFIG_HOME/typetui`, falling back to `~/.config/typetui`.
+pub fn config_dir() -> PathBuf {
+    xdg_dir("XDG_CONFIG_HOME", &[".config"])
+}
+
+/// Resolves the database path, a `--db` flag wins over `$TYPETUI_DB` which wins over the
+/// XDG data directory.
+pub fn db_path(flag: Option<&Path>) -> PathBuf {
+    if let Some(path) = flag {
+        return path.to_path_buf();
+    }
+    match env::var_os(DB_ENV_VAR) {
+        Some(path) if !path.is_empty() => PathBuf::from(path),
+        _ => data_dir().join(DB_FILE),
+    }
+}
+
+// the spec says relative values must be ignored
+fn xdg_dir(var: &str, home_fallback: &[&str]) -> PathBuf {
+    if let Some(dir) = env::var_os(var).map(PathBuf::from) {
+        if dir.is_absolute() {
+            return dir.join(APP_DIR);
+        }
+    }
+    let mut dir = env::var_os("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("."));
+    dir.extend(home_fallback);
+    dir.join(APP_DIR)
+}
@@ -0,0 +1,113 @@
+use std::{fs, sync::Arc};
+
+use rand::{seq::IndexedRandom, Rng};
+
+use crate::paths;
+
+/// Bundled quotes, `id<TAB>source<TAB>text` per line.
+const BUILTIN_QUOTES: &str = include_str!("../quotes.txt");
+const USER_QUOTES_FILE: &str = "quotes.txt";
+
+/// MonkeyType's quote length groups, by character count.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuoteLength {
+    Short,
+    Medium,
+    Long,
+    Thicc,
+}
+
+impl QuoteLength {
+    pub const ALL: [QuoteLength; 4] = [
+        QuoteLength::Short,
+        QuoteLength::Medium,
+        QuoteLength::Long,
+        QuoteLength::Thicc,
+    ];
+
+    pub fn of(text: &str) -> QuoteLength {
+        match text.chars().count() {
+            0..=100 => QuoteLength::Short,
+            101..=300 => QuoteLength::Medium,
+            301..=600 => QuoteLength::Long,
+            _ => QuoteLength::Thicc,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuoteLength::Short => "short",
+            QuoteLength::Medium => "medium",
+            QuoteLength::Long => "long",
+            QuoteLength::Thicc => "thicc",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Quote {
+    /// Stored with each result. Quotes from the user's file are prefixed with `user-` so they
+    /// never collide with bundled ones.
+    pub id: String,
+    pub source: String,
+    pub text: String,
+}
+
+impl Quote {
+    pub fn length(&self) -> QuoteLength {
+        QuoteLength::of(&self.text)
+    }
+}
+
+/// Bundled quotes plus the optional `<config dir>/quotes.txt`, loaded once at startup.
+#[derive(Clone, Debug, Default)]
+pub struct QuoteBook {
+    quotes: Vec<Arc<Quote>>,
+}
+
+impl QuoteBook {
+    pub fn load() -> QuoteBook {
+        let mut book = QuoteBook::default();
+        book.extend(BUILTIN_QUOTES, "");
+        if let Ok(contents) = fs::read_to_string(paths::config_dir().join(USER_QUOTES_FILE)) {
+            book.extend(&contents, "user-");
+        }
+        book
+    }
+
+    // malformed lines are skipped rather than failing the whole file
+    fn extend(&mut self, contents: &str, id_prefix: &str) {
+        let parsed = contents
+            .lines()
+            .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let mut fields = line.splitn(3, '\t');
+                let id = fields.next()?.trim();
+                let source = fields.next()?.trim();
+                let text = fields.next()?.trim();
+                if id.is_empty() || text.is_empty() {
+                    return None;
+                }
+                Some(Arc::new(Quote {
+                    id: format!("{}{}", id_prefix, id),
+                    source: source.to_string(),
+                    text: text.to_string(),
+                }))
+            });
+        self.quotes.extend(parsed);
+    }
+
+    pub fn count(&self, length: QuoteLength) -> usize {
+        self.quotes.iter().filter(|q| q.length() == length).count()
+    }
+
+    /// A random quote of the given length, `None` if there are none.
+    pub fn pick<R: Rng + ?Sized>(&self, rng: &mut R, length: QuoteLength) -> Option<Arc<Quote>> {
+        let matching: Vec<&Arc<Quote>> = self
+            .quotes
+            .iter()
+            .filter(|q| q.length() == length)
+            .collect();
+        matching.choose(rng).map(|q| Arc::clone(q))
+    }
+}
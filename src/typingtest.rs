@@ -1,12 +1,71 @@
 use std::{
-    fs,
+    sync::Arc,
     time::{Duration, Instant},
 };
 
 use crossterm::event::KeyCode;
-use rand::seq::IndexedRandom;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
 
-use crate::app::{Screen, TypeTui};
+use crate::{
+    app::{Screen, TypeTui},
+    code::{self, Language, Snippet, SnippetBook},
+    metrics::Metrics,
+    quotes::{Quote, QuoteBook, QuoteLength},
+    words::{self, WordBand, WordList},
+};
+
+/// How the test was started, stored with every result.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TestMode {
+    Words,
+    Time,
+    Quick,
+    Quote,
+    Custom,
+    Code,
+}
+
+impl TestMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TestMode::Words => "words",
+            TestMode::Time => "time",
+            TestMode::Quick => "quick",
+            TestMode::Quote => "quote",
+            TestMode::Custom => "custom",
+            TestMode::Code => "code",
+        }
+    }
+}
+
+/// What a single key press did to the input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeystrokeKind {
+    Correct,
+    Incorrect,
+    Backspace,
+}
+
+/// One entry of the per-test key log. `offset` is time on the clock, see `elapsed`,
+/// for a backspace `typed` is the character that was removed.
+#[derive(Clone, Copy, Debug)]
+pub struct Keystroke {
+    pub typed: char,
+    pub expected: Option<char>,
+    pub offset: Duration,
+    pub kind: KeystrokeKind,
+}
+
+impl KeystrokeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeystrokeKind::Correct => "correct",
+            KeystrokeKind::Incorrect => "incorrect",
+            KeystrokeKind::Backspace => "backspace",
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct TypingTest {
@@ -18,8 +77,29 @@ pub struct TypingTest {
     pub accuracy: i32,
     pub wpm: i32,
     pub time: Option<Duration>,
+    /// Set by the first character typed.
     pub start_time: Option<Instant>,
+    /// When the test was paused, the clock is stopped while this is set.
+    paused_at: Option<Instant>,
+    /// Time spent paused since `start_time`, left out of `elapsed`.
+    paused_total: Duration,
+    /// Byte offsets in `user_input` of indentation put in by Enter or Tab rather than typed,
+    /// none of it is scored.
+    free_indent: Vec<usize>,
     pub time_limit: Option<u16>,
+    pub mode: TestMode,
+    pub word_list: Arc<WordList>,
+    pub band: WordBand,
+    pub punctuation: bool,
+    pub numbers: bool,
+    pub quote: Option<Arc<Quote>>,
+    pub snippet: Option<Arc<Snippet>>,
+    /// Same seed and word list always generate the same text, appended words included.
+    pub seed: u64,
+    rng: ChaCha8Rng,
+    pub keystrokes: Vec<Keystroke>,
+    /// Filled in when the test finishes.
+    pub metrics: Metrics,
 }
 
 impl Default for TypingTest {
@@ -39,104 +119,360 @@ impl TypingTest {
             time: None,
             word_count: 0,
             start_time: None,
+            paused_at: None,
+            paused_total: Duration::ZERO,
+            free_indent: Vec::new(),
             time_limit: None,
+            mode: TestMode::Words,
+            word_list: WordList::builtin(),
+            band: WordBand::Full,
+            punctuation: false,
+            numbers: false,
+            quote: None,
+            snippet: None,
+            seed: 0,
+            rng: ChaCha8Rng::seed_from_u64(0),
+            keystrokes: Vec::new(),
+            metrics: Metrics::default(),
         }
     }
     pub fn handle_typing_input(key: KeyCode, app: &mut TypeTui) {
+        // a key that lands after the limit, before the tick noticed, doesn't count
+        if TypingTest::check_time_limit(app) {
+            return;
+        }
         let test = &mut app.typing;
-        let test_chars: Vec<char> = test.test_text.chars().collect();
+        let mut submitted = false;
         match key {
-            KeyCode::Char(c) => test.user_input.push(c),
-            KeyCode::Backspace => {
-                test.user_input.pop();
+            KeyCode::Char(' ') => submitted = test.next_word(),
+            KeyCode::Char(c) => test.type_char(c),
+            KeyCode::Enter if test.mode == TestMode::Code => {
+                let newline = test.separator() == Some('\n');
+                submitted = test.next_word();
+                // auto-indent: the next line's leading whitespace comes for free
+                if newline {
+                    while test.at_indent() {
+                        test.push_indent();
+                    }
+                }
             }
-            KeyCode::Esc => app.current_screen = Screen::Pause,
-            _ => {}
-        }
-        if let Some(limit_secs) = test.time_limit {
-            if test.start_time.is_none() {
-                test.start_time = Some(Instant::now());
-            }
-
-            if let Some(start) = test.start_time {
-                if start.elapsed().as_secs() >= limit_secs as u64 {
-                    test.time = Some(start.elapsed());
-
-                    test.correct_char = test_chars
-                        .iter()
-                        .zip(test.user_input.chars())
-                        .filter(|(e, a)| *e == a)
-                        .count() as i32;
-
-                    test.calculate_wpm_acc();
-                    test.word_count = (test.user_input.len() as i32) / 5;
-
-                    if app.user.is_empty() {
-                        app.current_screen = Screen::Login;
-                    } else {
-                        let time = limit_secs as i32;
-                        app.db.add_test(
-                            app.user.clone(),
-                            test.wpm,
-                            test.raw_wpm,
-                            test.accuracy,
-                            test.word_count,
-                            time,
-                        );
-                        app.history = app.db.get_all_tests().unwrap_or_default();
-                        app.current_screen = Screen::Stats;
+            KeyCode::Tab if test.mode == TestMode::Code => {
+                for _ in 0..code::TAB_WIDTH {
+                    if !test.at_indent() {
+                        break;
                     }
+                    test.push_indent();
                 }
             }
-
+            KeyCode::Backspace => {
+                // a line holding only auto-indentation goes away together with its newline
+                if test.mode == TestMode::Code {
+                    let line_start = test.user_input.rfind('\n').map_or(0, |i| i + 1);
+                    if line_start > 0 && test.user_input[line_start..].trim_start().is_empty() {
+                        test.user_input.truncate(line_start);
+                    }
+                }
+                // popping a separator puts the cursor back at the end of the previous word
+                if let Some(removed) = test.user_input.pop() {
+                    let expected = test.expected();
+                    test.record_keystroke(removed, expected, KeystrokeKind::Backspace);
+                }
+                let len = test.user_input.len();
+                test.free_indent.retain(|&i| i < len);
+            }
+            KeyCode::Esc => {
+                test.pause();
+                app.current_screen = Screen::Pause;
+                return;
+            }
+            _ => {}
+        }
+        // the clock starts with the first character, arrows and the like never start it
+        if test.start_time.is_none() && !test.user_input.is_empty() {
+            test.start_time = Some(Instant::now());
+        }
+        if test.time_limit.is_some() {
             //this appends words if it is a timed test and the user is almost out of words
             let progress = test.user_input.len() as f64 / test.test_text.len() as f64;
             if progress >= 0.75 {
                 test.append_words(10);
             }
         } else {
-            //start timer on first keystroke
-            if test.start_time.is_none() && !test.user_input.is_empty() {
-                test.start_time = Some(Instant::now());
-            }
-
-            if test.user_input.len() < test.test_text.len() {
+            // done once the last word is typed correctly, or submitted with space
+            if !submitted && !test.last_word_done() {
                 return;
             }
 
-            if let Some(start) = test.start_time {
-                test.time = Some(start.elapsed());
+            if test.start_time.is_some() {
+                test.time = Some(test.elapsed());
             }
 
-            test.correct_char = test_chars
-                .iter()
-                .zip(test.user_input.chars())
-                .filter(|(e, a)| *e == a)
-                .count() as i32;
+            test.correct_char = test.count_correct();
 
             test.calculate_wpm_acc();
             test.word_count = (test.test_text.len() as i32) / 5;
 
-            if app.user.is_empty() {
-                app.current_screen = Screen::Login;
-            } else {
-                let time = test.time_limit.unwrap_or(0) as i32;
-                app.db.add_test(
-                    app.user.clone(),
-                    test.wpm,
-                    test.raw_wpm,
-                    test.accuracy,
-                    test.word_count,
-                    time,
-                );
-                app.history = app.db.get_all_tests().unwrap_or_default();
-                app.current_screen = Screen::Stats;
+            app.complete_test();
+        }
+    }
+
+    /// Which results this test can fairly be compared with: the mode and its length, plus how
+    /// the words were generated for modes that generate them.
+    pub fn mode_key(&self) -> String {
+        let mut parts = vec![match self.mode {
+            // quick tests run on the clock too
+            TestMode::Time | TestMode::Quick if self.time_limit.is_some() => {
+                format!("time {}", self.time_limit.unwrap_or(0))
+            }
+            TestMode::Time | TestMode::Words | TestMode::Quick => {
+                format!("words {}", self.test_text.split_whitespace().count())
+            }
+            TestMode::Quote => format!("quote {}", QuoteLength::of(&self.test_text).as_str()),
+            TestMode::Code => format!(
+                "code {}",
+                self.snippet
+                    .as_ref()
+                    .map_or("other", |snippet| snippet.language.as_str())
+            ),
+            TestMode::Custom => "custom".to_string(),
+        }];
+        if matches!(
+            self.mode,
+            TestMode::Time | TestMode::Words | TestMode::Quick
+        ) {
+            parts.push(self.word_list.name.clone());
+            parts.push(self.band.as_str().to_string());
+            if self.punctuation {
+                parts.push("punctuation".to_string());
+            }
+            if self.numbers {
+                parts.push("numbers".to_string());
             }
         }
+        parts.join(" · ")
+    }
+
+    /// Ends a timed test once its limit has passed. Runs on every tick so the test finishes on
+    /// the clock rather than on the next key, returns true when it did.
+    pub fn check_time_limit(app: &mut TypeTui) -> bool {
+        let test = &mut app.typing;
+        let Some(limit_secs) = test.time_limit else {
+            return false;
+        };
+        let limit = Duration::from_secs(limit_secs as u64);
+        if test.start_time.is_none() || test.elapsed() < limit {
+            return false;
+        }
+        // measured against the limit itself, not whenever we noticed it had passed
+        test.time = Some(limit);
+        test.correct_char = test.count_correct();
+        test.calculate_wpm_acc();
+        test.word_count = (test.typed_chars() as i32) / 5;
+
+        app.complete_test();
+        true
+    }
+
+    /// Speed so far, for the status bar.
+    pub fn live_wpm(&self) -> i32 {
+        let mins = self.elapsed().as_secs_f64() / 60.0;
+        if mins > 0.0 {
+            (self.count_correct() as f64 / 5.0 / mins).round() as i32
+        } else {
+            0
+        }
+    }
+
+    /// Accuracy so far, `None` before anything is typed.
+    pub fn live_accuracy(&self) -> Option<i32> {
+        let typed = self.typed_chars();
+        (typed > 0).then(|| (self.count_correct() as f64 / typed as f64 * 100.0).round() as i32)
+    }
+
+    /// Words moved past and words in the test, indentation in code isn't counted.
+    pub fn words_done(&self) -> (usize, usize) {
+        let (index, _) = self.current_word();
+        let words = split_words(&self.test_text);
+        let count =
+            |words: &[(&str, Option<char>)]| words.iter().filter(|w| !w.0.is_empty()).count();
+        (count(&words[..index.min(words.len())]), count(&words))
+    }
+
+    /// Index of the word being typed and what has been typed of it so far.
+    fn current_word(&self) -> (usize, &str) {
+        // whitespace like a non-breaking space is more than one byte
+        let start = self
+            .user_input
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map_or(0, |(i, c)| i + c.len_utf8());
+        let index = self.user_input.matches(char::is_whitespace).count();
+        (index, &self.user_input[start..])
+    }
+
+    /// The char the cursor is on, the separator once the word is complete and `None` past
+    /// the end of it.
+    fn expected(&self) -> Option<char> {
+        let (index, typed) = self.current_word();
+        let words = split_words(&self.test_text);
+        let (word, sep) = words.get(index)?;
+        let typed_len = typed.chars().count();
+        match word.chars().nth(typed_len) {
+            Some(c) => Some(c),
+            None if typed_len == word.chars().count() => *sep,
+            None => None,
+        }
+    }
+
+    /// The whitespace that ends the word being typed.
+    fn separator(&self) -> Option<char> {
+        let (index, _) = self.current_word();
+        split_words(&self.test_text)
+            .get(index)
+            .and_then(|(_, sep)| *sep)
+    }
+
+    /// The cursor is on indentation, an empty word ended by a space.
+    fn at_indent(&self) -> bool {
+        let (index, typed) = self.current_word();
+        typed.is_empty()
+            && split_words(&self.test_text)
+                .get(index)
+                .is_some_and(|(word, sep)| word.is_empty() && *sep == Some(' '))
+    }
+
+    /// Adds a space of indentation without a key press to log or score.
+    fn push_indent(&mut self) {
+        self.free_indent.push(self.user_input.len());
+        self.user_input.push(' ');
+    }
+
+    /// Characters in the input that were typed, leaving out free indentation.
+    fn typed_chars(&self) -> usize {
+        self.user_input.chars().count() - self.free_indent.len()
+    }
+
+    /// Empties the input and key log to type the text again.
+    pub fn clear_input(&mut self) {
+        self.user_input.clear();
+        self.keystrokes.clear();
+        self.free_indent.clear();
+    }
+
+    fn type_char(&mut self, c: char) {
+        let expected = self.expected();
+        self.user_input.push(c);
+        let kind = if expected == Some(c) {
+            KeystrokeKind::Correct
+        } else {
+            KeystrokeKind::Incorrect
+        };
+        self.record_keystroke(c, expected, kind);
+    }
+
+    /// Moves on to the next word, leaving any letters not typed as missed. Ignored before the
+    /// first letter of a word, returns true when space was pressed on the last word.
+    fn next_word(&mut self) -> bool {
+        let (index, typed) = self.current_word();
+        let words = split_words(&self.test_text);
+        let Some(&(word, sep)) = words.get(index) else {
+            return false;
+        };
+        if typed.is_empty() && !word.is_empty() {
+            return false;
+        }
+        let kind = if typed == word {
+            KeystrokeKind::Correct
+        } else {
+            KeystrokeKind::Incorrect
+        };
+        let expected = self.expected();
+        // the input keeps the test's own separator so both split into the same words
+        match sep {
+            Some(sep) => {
+                self.user_input.push(sep);
+                self.record_keystroke(sep, expected, kind);
+                false
+            }
+            None => {
+                self.record_keystroke(' ', expected, kind);
+                true
+            }
+        }
+    }
+
+    fn last_word_done(&self) -> bool {
+        let words = split_words(&self.test_text);
+        let typed = split_words(&self.user_input);
+        typed.len() == words.len() && typed.last().map(|w| w.0) == words.last().map(|w| w.0)
+    }
+
+    /// Letters typed in the right place within their word, plus the separator after each
+    /// word typed exactly. Free indentation is always in place and taken back off.
+    fn count_correct(&self) -> i32 {
+        let words = split_words(&self.test_text);
+        let typed = split_words(&self.user_input);
+        let mut correct = 0;
+        for ((typed, typed_sep), (word, _)) in typed.iter().zip(&words) {
+            correct += typed
+                .chars()
+                .zip(word.chars())
+                .filter(|(t, w)| t == w)
+                .count();
+            if typed_sep.is_some() && typed == word {
+                correct += 1;
+            }
+        }
+        (correct - self.free_indent.len()) as i32
+    }
+
+    /// Time on the clock: since the first character, less any time spent paused. Zero if the
+    /// clock hasn't started yet.
+    pub fn elapsed(&self) -> Duration {
+        let Some(start) = self.start_time else {
+            return Duration::ZERO;
+        };
+        let now = self.paused_at.unwrap_or_else(Instant::now);
+        now.saturating_duration_since(start)
+            .saturating_sub(self.paused_total)
+    }
+
+    /// Stops the clock until `resume`.
+    pub fn pause(&mut self) {
+        if self.paused_at.is_none() {
+            self.paused_at = Some(Instant::now());
+        }
+    }
+
+    pub fn resume(&mut self) {
+        if let Some(paused_at) = self.paused_at.take() {
+            if self.start_time.is_some() {
+                self.paused_total += paused_at.elapsed();
+            }
+        }
+    }
+
+    /// Puts the clock back to not started.
+    pub fn reset_clock(&mut self) {
+        self.start_time = None;
+        self.time = None;
+        self.paused_at = None;
+        self.paused_total = Duration::ZERO;
+    }
+
+    fn record_keystroke(&mut self, typed: char, expected: Option<char>, kind: KeystrokeKind) {
+        let offset = self.elapsed();
+        self.keystrokes.push(Keystroke {
+            typed,
+            expected,
+            offset,
+            kind,
+        });
     }
 
     fn calculate_wpm_acc(&mut self) {
-        let total_typed = self.user_input.chars().count() as i32;
+        let total_typed = self.typed_chars() as i32;
         if total_typed > 0 {
             let pct = (self.correct_char as f64) / (total_typed as f64) * 100.0;
             self.accuracy = pct.round() as i32;
@@ -147,42 +483,80 @@ impl TypingTest {
             let mins = duration.as_secs_f64() / 60.0;
             if mins > 0.0 {
                 self.wpm = ((self.correct_char as f64) / 5.0 / mins).round() as i32;
-                self.raw_wpm = ((self.user_input.len() as f64) / 5.0 / mins).round() as i32;
+                self.raw_wpm = ((total_typed as f64) / 5.0 / mins).round() as i32;
             }
         }
     }
 
+    fn sample_words(&mut self, num_words: usize, sentence_start: bool) -> String {
+        let words = self.word_list.sample(&mut self.rng, num_words, self.band);
+        words::dress_words(
+            &words,
+            &mut self.rng,
+            self.punctuation,
+            self.numbers,
+            sentence_start,
+        )
+        .join(" ")
+    }
+
+    /// Generates a fresh text from `seed`, restarting the word sequence from the beginning.
     pub fn get_words(&mut self, num_words: usize) {
-        let contents = fs::read_to_string("./20k.txt").expect("can't get words from file");
-        let words: Vec<&str> = contents
-            .lines()
-            .filter(|line| !line.trim().is_empty())
-            .collect();
-        let mut rng = rand::rng();
-        let chosen: Vec<&str> = words
-            .choose_multiple(&mut rng, num_words.min(words.len()))
-            .cloned()
-            .collect();
-        self.test_text = chosen.join(" ");
+        self.rng = ChaCha8Rng::seed_from_u64(self.seed);
+        self.test_text = self.sample_words(num_words, true);
+    }
+    /// Uses a quote of the given length picked with `seed` as the text, returns false when
+    /// there are no quotes of that length.
+    pub fn get_quote(&mut self, book: &QuoteBook, length: QuoteLength) -> bool {
+        self.rng = ChaCha8Rng::seed_from_u64(self.seed);
+        self.quote = book.pick(&mut self.rng, length);
+        match &self.quote {
+            Some(quote) => {
+                self.test_text = quote.text.clone();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Uses a snippet in the given language picked with `seed` as the text, returns false when
+    /// there are none.
+    pub fn get_snippet(&mut self, book: &SnippetBook, language: Language) -> bool {
+        self.rng = ChaCha8Rng::seed_from_u64(self.seed);
+        self.snippet = book.pick(&mut self.rng, language);
+        match &self.snippet {
+            Some(snippet) => {
+                self.test_text = snippet.text.clone();
+                true
+            }
+            None => false,
+        }
     }
+
     pub fn append_words(&mut self, num_words: usize) {
-        let contents = fs::read_to_string("./20k.txt").expect("can't get words from file");
-        let words: Vec<&str> = contents
-            .lines()
-            .filter(|line| !line.trim().is_empty())
-            .collect();
-        let mut rng = rand::rng();
-        let chosen: Vec<&str> = words
-            .choose_multiple(&mut rng, num_words.min(words.len()))
-            .cloned()
-            .collect();
+        let chosen = self.sample_words(num_words, words::ends_sentence(&self.test_text));
 
         if !self.test_text.is_empty() {
             self.test_text.push(' ');
         }
-        self.test_text.push_str(&chosen.join(" "));
+        self.test_text.push_str(&chosen);
+    }
+}
+/// Splits a text into words, each with the whitespace char that ends it, `None` for the last.
+/// Runs of whitespace give empty words, which is how indentation in code lines up.
+pub fn split_words(text: &str) -> Vec<(&str, Option<char>)> {
+    let mut words = Vec::new();
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        if c.is_whitespace() {
+            words.push((&text[start..i], Some(c)));
+            start = i + c.len_utf8();
+        }
     }
+    words.push((&text[start..], None));
+    words
 }
+
 //counting chars helper function
 pub fn count_chars(input: &str) -> usize {
     input
@@ -190,3 +564,138 @@ pub fn count_chars(input: &str) -> usize {
         .take_while(|ch| ch.is_whitespace() || ch.is_alphabetic() && *ch != '\n')
         .count()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test(text: &str, input: &str) -> TypingTest {
+        let mut test = TypingTest::new();
+        test.test_text = text.to_string();
+        test.user_input = input.to_string();
+        test
+    }
+
+    /// Types `keys` the way the typing screen does, space moves on to the next word.
+    fn typed(text: &str, keys: &str) -> TypingTest {
+        let mut test = test(text, "");
+        for c in keys.chars() {
+            if c == ' ' {
+                test.next_word();
+            } else {
+                test.type_char(c);
+            }
+        }
+        test
+    }
+
+    /// Presses `keys` on the typing screen of a code test over `text`.
+    fn pressed(text: &str, keys: &[KeyCode]) -> TypeTui {
+        let db = crate::db::DB::new(std::path::Path::new(":memory:")).unwrap();
+        let mut app = TypeTui::new(db);
+        app.typing.mode = TestMode::Code;
+        app.typing.test_text = text.to_string();
+        for &key in keys {
+            TypingTest::handle_typing_input(key, &mut app);
+        }
+        app
+    }
+
+    #[test]
+    fn indentation_is_free() {
+        use KeyCode::{Backspace, Char, Enter, Tab};
+        let cases = [
+            // (keys, input)
+            (vec![Char('a'), Enter, Char('b')], "a\n        b"),
+            // space ends the line without indenting, each Tab indents up to four
+            (
+                vec![Char('a'), Char(' '), Tab, Tab, Char('b')],
+                "a\n        b",
+            ),
+        ];
+        for (keys, input) in cases {
+            let app = pressed("a\n        b", &keys);
+            let test = &app.typing;
+            assert_eq!(test.user_input, input, "{:?}", keys);
+            assert_eq!(test.correct_char, 3, "{:?}", keys);
+            assert_eq!(test.accuracy, 100, "{:?}", keys);
+            assert_eq!(test.keystrokes.len(), 3, "{:?}", keys);
+        }
+
+        // backspacing over an indented line takes its indentation with it
+        let app = pressed(
+            "a\n    b c",
+            &[Char('a'), Enter, Backspace, Enter, Char('x')],
+        );
+        assert_eq!(app.typing.user_input, "a\n    x");
+        assert_eq!(app.typing.typed_chars(), 3);
+        assert_eq!(app.typing.count_correct(), 2);
+    }
+
+    #[test]
+    fn current_word_after_multibyte_whitespace() {
+        let test = test("h e", "h\u{a0}e");
+        assert_eq!(test.current_word(), (1, "e"));
+        assert_eq!(test.words_done(), (1, 2));
+    }
+
+    #[test]
+    fn count_correct() {
+        let cases = [
+            // (text, input, correct)
+            ("the cat", "", 0),
+            ("the cat", "the ", 4),
+            ("the cat", "the cat", 7),
+            // a wrong letter doesn't shift the rest of the word
+            ("the cat", "tho cat", 5),
+            // extra letters and the separator after a wrong word don't count
+            ("the cat", "thee cat", 6),
+            // skipped letters only lose themselves
+            ("the cat", "th cat", 5),
+        ];
+        for (text, input, correct) in cases {
+            assert_eq!(test(text, input).count_correct(), correct, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn next_word() {
+        let cases = [
+            // (text, keys, input)
+            ("the cat", "the cat", "the cat"),
+            // space before the first letter of a word is ignored
+            ("the cat", " the  cat", "the cat"),
+            // skipping ahead leaves the rest of the word missed
+            ("the cat", "th cat", "th cat"),
+            ("the cat", "theee cat", "theee cat"),
+            // the test's own separator goes into the input
+            ("a\nb", "a b", "a\nb"),
+        ];
+        for (text, keys, input) in cases {
+            assert_eq!(typed(text, keys).user_input, input, "{:?}", keys);
+        }
+    }
+
+    #[test]
+    fn next_word_submits_on_the_last_word() {
+        let mut test = typed("the cat", "the ca");
+        assert!(test.next_word());
+        assert_eq!(test.user_input, "the ca");
+        assert!(!typed("the cat", "th").next_word());
+    }
+
+    #[test]
+    fn last_word_done() {
+        let cases = [
+            // (text, input, done)
+            ("the cat", "the cat", true),
+            ("the cat", "the ca", false),
+            ("the cat", "the catt", false),
+            ("the cat", "th cat", true),
+            ("the cat", "the", false),
+        ];
+        for (text, input, done) in cases {
+            assert_eq!(test(text, input).last_word_done(), done, "{:?}", input);
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::{error::Error, io};
+use std::{error::Error, io, time::Duration};
 
 use crossterm::{
     event::{DisableMouseCapture, EnableMouseCapture},
@@ -9,18 +9,24 @@ use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     prelude::CrosstermBackend,
     style::{Color, Modifier, Style},
+    symbols,
     text::{Line, Span, Text},
     widgets::{
-        Block, BorderType, Borders, Cell, List, ListItem, ListState, Paragraph, Row, Table,
-        TableState, Wrap,
+        Axis, Block, BorderType, Borders, Cell, Chart, Dataset, GraphType, List, ListItem,
+        ListState, Padding, Paragraph, Row, Table, TableState, Wrap,
     },
     Frame, Terminal,
 };
 
 use crate::{
-    app::{AppResult, Screen, TestOptsFocus, TypeTui},
+    app::{AppResult, LoginStep, MenuItem, Screen, TestOption, TestOpts, TestOptsFocus, TypeTui},
+    db::TestRow,
     event::AppEventHandler,
-    typingtest::TypingTest,
+    highlight::{self, Token},
+    history::{test_kind, HistoryFilter, SortColumn},
+    metrics::Sample,
+    quotes::QuoteLength,
+    typingtest::{self, TestMode, TypingTest},
 };
 
 //our ui struct will handle all of the terminal stuff,
@@ -80,50 +86,245 @@ pub fn render_title(frame: &mut Frame, chunk: Rect) -> AppResult<()> {
     Ok(())
 }
 
-pub fn render_typing_test(frame: &mut Frame, chunk: Rect, typing: &TypingTest) -> AppResult<()> {
-    let input_chars = typing.user_input.chars();
+pub fn render_typing_test(
+    frame: &mut Frame,
+    chunk: Rect,
+    typing: &TypingTest,
+    visible_lines: usize,
+) -> AppResult<()> {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Color::LightBlue)
+        .border_type(BorderType::QuadrantInside)
+        .padding(Padding::horizontal(1));
+    let inner = block.inner(chunk);
+    frame.render_widget(block, chunk);
+
+    // one column is kept free so a trailing space the cursor sits on stays visible
+    let width = (inner.width as usize).saturating_sub(1).max(1);
+    // a snippet is read as a whole, so code gets every line that fits
+    let visible = if typing.snippet.is_some() {
+        inner.height as usize
+    } else {
+        visible_lines.min(inner.height as usize)
+    }
+    .max(1);
+    let (lines, cursor_line) = layout_lines(display_words(typing), width, visible);
+
+    // keep the cursor line in the middle of the window, except near the ends of the text
+    let before = (visible - 1) / 2;
+    let first = cursor_line
+        .saturating_sub(before)
+        .min(lines.len().saturating_sub(visible));
+    let window: Vec<Line> = lines.into_iter().skip(first).take(visible).collect();
+
+    let top = if typing.snippet.is_some() {
+        0
+    } else {
+        inner.height.saturating_sub(window.len() as u16) / 2
+    };
+    let area = Rect {
+        y: inner.y + top,
+        height: inner.height - top,
+        ..inner
+    };
+    frame.render_widget(Paragraph::new(window), area);
+    Ok(())
+}
 
-    let spans: Vec<Span> = typing
-        .test_text
-        .chars()
-        .zip(input_chars.clone().map(Some).chain(std::iter::repeat(None)))
+/// Live figures above the text, each part can be turned off in the test options.
+fn render_status_bar(frame: &mut Frame, area: Rect, typing: &TypingTest, opts: &TestOpts) {
+    let mut parts = Vec::new();
+    match typing.time_limit {
+        Some(limit) if opts.show_timer => {
+            let left = Duration::from_secs(limit as u64).saturating_sub(typing.elapsed());
+            parts.push(format!("{}s", left.as_secs_f64().ceil()));
+        }
+        Some(_) => {}
+        None if opts.show_progress => {
+            let (done, total) = typing.words_done();
+            parts.push(format!("{}/{}", done, total));
+        }
+        None => {}
+    }
+    if opts.show_wpm {
+        parts.push(format!("{} wpm", typing.live_wpm()));
+    }
+    if opts.show_accuracy {
+        match typing.live_accuracy() {
+            Some(accuracy) => parts.push(format!("{}% acc", accuracy)),
+            None => parts.push("- acc".to_string()),
+        }
+    }
+    let p = Paragraph::new(parts.join("   "))
+        .style(Style::default().fg(Color::LightBlue))
+        .alignment(Alignment::Center);
+    frame.render_widget(p, area);
+}
+
+/// A word of the test as it is drawn, its trailing separator included, so lines can be
+/// broken between words.
+struct DisplayWord {
+    glyphs: Vec<Span<'static>>,
+    /// Index into `glyphs` the cursor is on.
+    cursor: Option<usize>,
+    /// The word ends in a newline, whatever follows starts a new line.
+    hard_break: bool,
+}
+
+fn display_words(typing: &TypingTest) -> Vec<DisplayWord> {
+    let typed_words = typingtest::split_words(&typing.user_input);
+    let current = typed_words.len() - 1;
+    // code is coloured by syntax until it's typed over
+    let tokens = typing
+        .snippet
+        .as_ref()
+        .map(|snippet| highlight::highlight(&typing.test_text, snippet.language));
+    let cursor_style = Style::default()
+        .fg(Color::LightBlue)
+        .add_modifier(Modifier::REVERSED);
+
+    let mut words = Vec::new();
+    // char index of the word in the test text, for looking up its tokens
+    let mut offset = 0;
+    for (i, (expected, sep)) in typingtest::split_words(&typing.test_text)
+        .into_iter()
         .enumerate()
-        .map(|(i, (test_char, input_char))| {
-            let style = match input_char {
-                Some(c) if c == test_char => Style::default().fg(Color::Green),
-                Some(_) => Style::default().fg(Color::Red),
-                None if i == typing.user_input.len() => Style::default()
-                    .fg(Color::LightBlue)
-                    .add_modifier(Modifier::REVERSED),
-                None => Style::default(),
+    {
+        let expected: Vec<char> = expected.chars().collect();
+        let typed: Vec<char> = typed_words
+            .get(i)
+            .map_or(Vec::new(), |(word, _)| word.chars().collect());
+        let mut word = DisplayWord::new();
+        for j in 0..expected.len().max(typed.len()) {
+            // letters past the end of the word are drawn as typed
+            let ch = *expected.get(j).or(typed.get(j)).unwrap_or(&' ');
+            let style = match typed.get(j) {
+                Some(t) if expected.get(j) == Some(t) => Style::default().fg(Color::Green),
+                Some(_) if j < expected.len() => Style::default().fg(Color::Red),
+                Some(_) => Style::default().fg(Color::LightRed),
+                None if i < current => Style::default()
+                    .fg(Color::Red)
+                    .add_modifier(Modifier::UNDERLINED),
+                None if i == current && j == typed.len() => {
+                    word.cursor = Some(word.glyphs.len());
+                    cursor_style
+                }
+                None => tokens
+                    .as_ref()
+                    .map_or(Style::default(), |tokens| token_style(tokens[offset + j])),
             };
-            Span::styled(test_char.to_string(), style)
-        })
-        .collect();
+            word.glyphs.push(Span::styled(ch.to_string(), style));
+        }
+        let on_sep = i == current && typed.len() >= expected.len();
+        let sep_style = if on_sep {
+            word.cursor = Some(word.glyphs.len());
+            cursor_style
+        } else {
+            Style::default()
+        };
+        match sep {
+            Some('\n') => {
+                // a newline has no glyph, draw a marker when the cursor is on it
+                if on_sep {
+                    word.glyphs.push(Span::styled("\u{21b5}", sep_style));
+                }
+                word.hard_break = true;
+            }
+            Some(_) => word.glyphs.push(Span::styled(" ", sep_style)),
+            None => {}
+        }
+        offset += expected.len() + 1;
+        words.push(word);
+    }
+    words
+}
 
-    let paragraph = Paragraph::new(Line::from(spans))
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_style(Color::LightBlue)
-                .border_type(BorderType::QuadrantInside),
-        )
-        .alignment(ratatui::layout::Alignment::Center)
-        .wrap(Wrap { trim: true });
+impl DisplayWord {
+    fn new() -> Self {
+        DisplayWord {
+            glyphs: Vec::new(),
+            cursor: None,
+            hard_break: false,
+        }
+    }
+}
 
-    frame.render_widget(paragraph, chunk);
-    Ok(())
+/// Fills lines of `width` columns word by word, returning them with the index of the line
+/// holding the cursor. Layout stops once `visible` lines past the cursor are done.
+fn layout_lines(
+    words: Vec<DisplayWord>,
+    width: usize,
+    visible: usize,
+) -> (Vec<Line<'static>>, usize) {
+    let mut lines = Vec::new();
+    let mut line = Vec::new();
+    let mut line_width = 0;
+    let mut cursor_line = None;
+    for word in words {
+        if cursor_line.is_some_and(|c| lines.len() > c + visible) {
+            break;
+        }
+        // a trailing space may use the spare column instead of pushing the word down
+        let trailing_space = word.glyphs.last().is_some_and(|g| g.content == " ");
+        let needed = word.glyphs.len() - trailing_space as usize;
+        if line_width > 0 && line_width + needed > width {
+            lines.push(Line::from(std::mem::take(&mut line)));
+            line_width = 0;
+        }
+        let last = word.glyphs.len().saturating_sub(1);
+        for (i, glyph) in word.glyphs.into_iter().enumerate() {
+            // words wider than a whole line are cut wherever the edge falls
+            if line_width >= width && !(i == last && trailing_space) {
+                lines.push(Line::from(std::mem::take(&mut line)));
+                line_width = 0;
+            }
+            if word.cursor == Some(i) {
+                cursor_line = Some(lines.len());
+            }
+            line.push(glyph);
+            line_width += 1;
+        }
+        if word.hard_break {
+            lines.push(Line::from(std::mem::take(&mut line)));
+            line_width = 0;
+        }
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(Line::from(line));
+    }
+    // the cursor is past the end once the text is done
+    let cursor_line = cursor_line.unwrap_or(lines.len() - 1);
+    (lines, cursor_line)
 }
 
-pub fn render_menu(frame: &mut Frame, chunk: Rect, selected_option: usize) -> AppResult<()> {
-    let options = ["Quick Test", "Login", "Test History", "Quit", "TestOpts"];
+fn token_style(token: Token) -> Style {
+    match token {
+        Token::Plain => Style::default(),
+        Token::Keyword => Style::default().fg(Color::Magenta),
+        Token::String => Style::default().fg(Color::Yellow),
+        Token::Comment => Style::default().fg(Color::DarkGray),
+        Token::Number => Style::default().fg(Color::Cyan),
+    }
+}
 
-    let items: Vec<ListItem> = options.iter().map(|&s| ListItem::new(s)).collect();
+pub fn render_menu(
+    frame: &mut Frame,
+    chunk: Rect,
+    options: &[MenuItem],
+    selected_option: usize,
+    user: Option<&str>,
+) -> AppResult<()> {
+    let items: Vec<ListItem> = options.iter().map(|s| ListItem::new(s.label())).collect();
 
+    let title = match user {
+        Some(user) => format!("Main Menu · {}", user),
+        None => "Main Menu · guest".to_string(),
+    };
     let popup_block = Block::default()
         .borders(Borders::ALL)
         .border_style(Color::LightBlue)
-        .title("Main Menu")
+        .title(title)
         .title_style(Color::LightBlue)
         .title_alignment(ratatui::layout::Alignment::Center);
 
@@ -141,77 +342,309 @@ pub fn render_menu(frame: &mut Frame, chunk: Rect, selected_option: usize) -> Ap
 pub fn render_history(
     frame: &mut Frame,
     area: Rect,
-    history: &[(String, i32, i32, i32, i32, i32)],
+    history: &[TestRow],
+    filter: &HistoryFilter,
     state: &mut TableState,
+    start: usize,
+    total: usize,
 ) -> AppResult<()> {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("History")
+        .border_style(Style::default().bg(Color::Blue))
+        .title_alignment(ratatui::layout::Alignment::Center);
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    let areas = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(2),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .split(inner);
+
+    let gray = Style::default().fg(Color::Gray);
+    let filters = Line::from(vec![
+        Span::styled("user ", gray),
+        Span::raw(filter.user.label().to_string()),
+        Span::styled(" · mode ", gray),
+        Span::raw(filter.mode.unwrap_or("all")),
+        Span::styled(" · length ", gray),
+        Span::raw(
+            filter
+                .length
+                .as_deref()
+                .and_then(|kind| kind.split_once(' '))
+                .map_or("all", |(_, length)| length)
+                .to_string(),
+        ),
+        Span::styled(" · ", gray),
+        Span::raw(filter.dates.label()),
+    ]);
+    frame.render_widget(
+        Paragraph::new(filters).alignment(Alignment::Center),
+        areas[0],
+    );
+
+    // the sorted column is marked with the direction it's sorted in
+    let arrow = if filter.descending {
+        " \u{2193}"
+    } else {
+        " \u{2191}"
+    };
+    let heading = |title: &str, column: Option<SortColumn>| {
+        let sorted = column == Some(filter.sort);
+        let text = if sorted {
+            format!("{}{}", title, arrow)
+        } else {
+            title.to_string()
+        };
+        let color = if sorted {
+            Color::LightBlue
+        } else {
+            Color::Blue
+        };
+        Cell::from(text).style(Style::default().fg(color))
+    };
     let header = Row::new(vec![
-        Cell::from("Time/Words").style(Style::default().fg(Color::Blue)),
-        Cell::from("Accuracy").style(Style::default().fg(Color::Blue)),
-        Cell::from("WPM").style(Style::default().fg(Color::LightBlue)),
-        Cell::from("Raw WPM").style(Style::default().fg(Color::Blue)),
-        Cell::from("User").style(Style::default().fg(Color::Blue)),
+        heading("Date", Some(SortColumn::Date)),
+        heading("Test", None),
+        heading("WPM", Some(SortColumn::Wpm)),
+        heading("Raw", Some(SortColumn::RawWpm)),
+        heading("Accuracy", Some(SortColumn::Accuracy)),
+        heading("User", None),
     ])
     .bottom_margin(1);
 
     let widths = &[
-        Constraint::Percentage(20),
-        Constraint::Percentage(20),
-        Constraint::Percentage(20),
-        Constraint::Percentage(20),
-        Constraint::Percentage(20),
+        Constraint::Length(16),
+        Constraint::Min(10),
+        Constraint::Length(6),
+        Constraint::Length(6),
+        Constraint::Length(10),
+        Constraint::Min(8),
     ];
 
-    let rows = history
-        .iter()
-        .map(|(user, wpm, raw_wpm, accuracy, word_count, time)| {
-            let first_col = if *time > 0 {
-                format!("{}s", time)
-            } else {
-                format!("{}w", word_count)
-            };
-            Row::new(vec![
-                Cell::from(first_col),
-                Cell::from(accuracy.to_string() + &String::from("%")),
-                Cell::from(wpm.to_string()),
-                Cell::from(raw_wpm.to_string()),
-                Cell::from(user.clone()),
-            ])
-        });
+    let rows = history.iter().map(|row| {
+        // unclaimed guest results stand out until someone saves them
+        let user = match row.guest_id {
+            Some(_) => Cell::from("guest").style(Style::default().fg(Color::Yellow)),
+            None => Cell::from(row.username.clone()),
+        };
+        Row::new(vec![
+            Cell::from(row.date.clone().unwrap_or_default()),
+            Cell::from(test_kind(&row.mode_key).to_string()),
+            Cell::from(row.wpm.to_string()),
+            Cell::from(row.raw_wpm.to_string()),
+            Cell::from(row.accuracy.to_string() + &String::from("%")),
+            user,
+        ])
+    });
 
     let table = Table::new(rows, widths)
         .header(header)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title("History")
-                .border_style(Style::default().bg(Color::Blue))
-                .title_alignment(ratatui::layout::Alignment::Center),
-        )
         .column_spacing(2)
         .row_highlight_style(Style::default().fg(Color::LightBlue));
 
-    frame.render_stateful_widget(table, area, state);
+    frame.render_stateful_widget(table, areas[1], state);
+
+    let footer = if history.is_empty() {
+        "no results".to_string()
+    } else {
+        format!(
+            "rows {}-{} of {} · PgUp/PgDn Home/End",
+            start + 1,
+            start + history.len(),
+            total
+        )
+    };
+    frame.render_widget(
+        Paragraph::new(footer)
+            .style(gray)
+            .alignment(Alignment::Center),
+        areas[2],
+    );
     Ok(())
 }
-pub fn render_stats(frame: &mut Frame, test: &TypingTest) -> AppResult<()> {
-    let wpm = test.wpm;
-    let popup_block = Block::default()
+pub fn render_stats(frame: &mut Frame, area: Rect, app: &TypeTui) -> AppResult<()> {
+    let test = &app.typing;
+    let block = Block::default()
         .borders(Borders::ALL)
         .border_style(Color::LightBlue)
-        .title("Stats")
+        .title("Results")
         .title_alignment(ratatui::layout::Alignment::Center);
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let gray = Style::default().fg(Color::Gray);
+    let chars = test.metrics.chars;
+    let mut lines = vec![
+        Line::styled(
+            format!("{} WPM", test.wpm),
+            Style::default()
+                .fg(Color::LightMagenta)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Line::styled(
+            format!(
+                "raw {} · accuracy {}% · consistency {}%",
+                test.raw_wpm, test.accuracy, test.metrics.consistency
+            ),
+            gray,
+        ),
+        Line::styled(
+            format!(
+                "characters {}/{}/{}/{} (correct/incorrect/extra/missed)",
+                chars.correct, chars.incorrect, chars.extra, chars.missed
+            ),
+            gray,
+        ),
+        Line::styled(
+            format!(
+                "{} · {:.1}s",
+                test.mode_key(),
+                test.time.unwrap_or_default().as_secs_f64()
+            ),
+            gray,
+        ),
+    ];
+    match (&test.quote, &test.snippet) {
+        (Some(quote), _) => {
+            lines.push(Line::styled(format!("\u{2014} {}", quote.source), gray));
+            if let Some(best) = app.quote_best {
+                lines.push(Line::styled(
+                    format!("best on this quote: {} WPM", best),
+                    gray,
+                ));
+            }
+        }
+        (None, Some(snippet)) => lines.push(Line::styled(
+            format!("{} · seed {}", snippet.source, test.seed),
+            gray,
+        )),
+        (None, None) if test.mode == TestMode::Custom => {
+            if let Some(custom) = &app.custom {
+                lines.push(Line::styled(
+                    format!(
+                        "{} · section {}/{}",
+                        custom.name,
+                        app.custom_section + 1,
+                        custom.len()
+                    ),
+                    gray,
+                ));
+            }
+        }
+        (None, None) => lines.push(Line::styled(format!("seed {}", test.seed), gray)),
+    }
+    // a first result is a best by default, that's no news
+    if app.new_best && app.previous_best.is_some() {
+        lines.insert(
+            0,
+            Line::styled(
+                "New personal best!",
+                Style::default()
+                    .fg(Color::LightYellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
+        );
+    }
+    lines.push(match (app.previous_best, app.new_best) {
+        _ if app.user.is_none() => Line::styled(
+            "guest result, claim it from Test History to keep it",
+            Style::default().fg(Color::Yellow),
+        ),
+        (Some(best), true) => Line::styled(format!("previous best {} WPM", best), gray),
+        (Some(best), false) => Line::styled(
+            format!("personal best {} WPM ({:+})", best, test.wpm - best),
+            gray,
+        ),
+        (None, _) => Line::styled("first result for this kind of test", gray),
+    });
 
-    let fmt_wpm = format!("{} WPM", wpm);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(lines.len() as u16 + 1),
+            Constraint::Min(0),
+        ])
+        .split(inner);
+    let summary = Paragraph::new(Text::from(lines)).alignment(Alignment::Center);
+    frame.render_widget(summary, chunks[0]);
+    render_speed_chart(frame, chunks[1], &test.metrics.samples);
+    Ok(())
+}
 
-    let wpm_span = Text::styled(fmt_wpm, Style::default().fg(Color::LightMagenta));
+/// WPM and raw WPM for each second of the test, seconds with mistakes are marked on the raw
+/// line.
+fn render_speed_chart(frame: &mut Frame, area: Rect, samples: &[Sample]) {
+    if samples.is_empty() || area.height < 5 {
+        return;
+    }
+    let wpm: Vec<(f64, f64)> = samples
+        .iter()
+        .map(|s| (s.second as f64, s.wpm as f64))
+        .collect();
+    let raw: Vec<(f64, f64)> = samples
+        .iter()
+        .map(|s| (s.second as f64, s.raw_wpm as f64))
+        .collect();
+    let errors: Vec<(f64, f64)> = samples
+        .iter()
+        .filter(|s| s.errors > 0)
+        .map(|s| (s.second as f64, s.raw_wpm as f64))
+        .collect();
 
-    let paragraph = Paragraph::new(wpm_span)
-        .block(popup_block)
-        .alignment(ratatui::layout::Alignment::Center);
+    let seconds = samples.len().max(2) as f64;
+    let top = samples
+        .iter()
+        .map(|s| s.wpm.max(s.raw_wpm))
+        .max()
+        .unwrap_or(0);
+    // headroom above the fastest second, rounded up to a multiple of 20
+    let top = ((top / 20 + 1) * 20) as f64;
 
-    let area = centered_rect(60, 25, frame.area());
-    frame.render_widget(paragraph, area);
-    Ok(())
+    let datasets = vec![
+        Dataset::default()
+            .name("raw")
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::DarkGray))
+            .data(&raw),
+        Dataset::default()
+            .name("wpm")
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::LightMagenta))
+            .data(&wpm),
+        Dataset::default()
+            .name("errors")
+            .marker(symbols::Marker::Dot)
+            .graph_type(GraphType::Scatter)
+            .style(Style::default().fg(Color::Red))
+            .data(&errors),
+    ];
+    let chart = Chart::new(datasets)
+        .x_axis(
+            Axis::default()
+                .title("seconds")
+                .style(Style::default().fg(Color::Gray))
+                .bounds([1.0, seconds])
+                .labels(["1".to_string(), format!("{}", seconds as u32)]),
+        )
+        .y_axis(
+            Axis::default()
+                .title("wpm")
+                .style(Style::default().fg(Color::Gray))
+                .bounds([0.0, top])
+                .labels([
+                    "0".to_string(),
+                    format!("{}", top as i32 / 2),
+                    format!("{}", top as i32),
+                ]),
+        );
+    frame.render_widget(chart, area);
 }
 
 pub fn render_quit(frame: &mut Frame) -> AppResult<()> {
@@ -278,27 +711,46 @@ pub fn ui(f: &mut Frame, app: &mut TypeTui) -> crate::app::AppResult<()> {
                 .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
                 .split(chunks[1]);
             render_splash(f, main_panes[0]);
-            render_menu(f, main_panes[1], selected_option)?;
+            render_menu(
+                f,
+                main_panes[1],
+                &app.menu_items(),
+                selected_option,
+                app.user.as_ref().map(|user| user.username.as_str()),
+            )?;
         }
         Screen::TestOpts => {
             let _ = render_test_opts(f, app);
         }
         Screen::Typing => {
-            render_typing_test(f, chunks[1], &app.typing)?;
+            let areas = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Min(3)])
+                .split(chunks[1]);
+            render_status_bar(f, areas[0], &app.typing, &app.test_opts);
+            render_typing_test(f, areas[1], &app.typing, app.test_opts.visible_lines)?;
         }
         Screen::Quit => {
             render_quit(f)?;
         }
         Screen::History => {
-            let area = centered_rect(50, 50, f.area());
-            let _ = render_history(f, area, &app.history, &mut app.stats_list_state);
+            let area = centered_rect(80, 70, f.area());
+            let _ = render_history(
+                f,
+                area,
+                &app.history,
+                &app.history_filter,
+                &mut app.stats_list_state,
+                app.history_start,
+                app.history_total,
+            );
         }
         Screen::Stats => {
-            render_stats(f, &app.typing)?;
+            render_stats(f, chunks[1], app)?;
         }
         Screen::Login => {
             let area = centered_rect(50, 20, f.area());
-            render_login(f, area, &app.login_input)?;
+            render_login(f, area, app)?;
         }
         Screen::Pause => {
             render_pause_menu(f, chunks[1], app.pause_selected)?;
@@ -307,6 +759,21 @@ pub fn ui(f: &mut Frame, app: &mut TypeTui) -> crate::app::AppResult<()> {
     match app.current_screen {
         Screen::Typing => render_legend(f, chunks[2], "ESC to pause"),
         Screen::TestOpts => render_legend(f, chunks[2], "ESC to return to Main Menu q to quit"),
+        Screen::Stats if app.typing.mode == TestMode::Custom => render_legend(
+            f,
+            chunks[2],
+            "Enter for the next section * ESC to return to Main Menu * q to quit",
+        ),
+        Screen::History if app.history.iter().any(|row| row.guest_id.is_some()) => render_legend(
+            f,
+            chunks[2],
+            "u user * m mode * l length * d dates * s sort * r reverse * c claim guest result * ESC back",
+        ),
+        Screen::History => render_legend(
+            f,
+            chunks[2],
+            "u user * m mode * l length * d dates * s sort * r reverse * ESC back * q to quit",
+        ),
         Screen::Main { selected_option: _ } => render_legend(
             f,
             chunks[2],
@@ -381,41 +848,81 @@ pub fn render_pause_menu(frame: &mut Frame, area: Rect, selected: usize) -> AppR
     Ok(())
 }
 
-pub fn render_login(frame: &mut Frame, area: Rect, user_input: &str) -> AppResult<()> {
+pub fn render_login(frame: &mut Frame, area: Rect, app: &TypeTui) -> AppResult<()> {
+    let (title, prompt, masked) = match &app.login_step {
+        LoginStep::Username => ("Login", "Enter your username".to_string(), false),
+        LoginStep::Passphrase { username, .. } => {
+            ("Login", format!("Passphrase for {}", username), true)
+        }
+        LoginStep::CurrentPassphrase => ("Passphrase", "Current passphrase".to_string(), true),
+        LoginStep::NewPassphrase => (
+            "Passphrase",
+            "New passphrase, leave empty to remove it".to_string(),
+            true,
+        ),
+        LoginStep::ConfirmPassphrase { .. } => {
+            ("Passphrase", "Type it again to confirm".to_string(), true)
+        }
+    };
     let block = Block::default()
         .borders(Borders::ALL)
         .border_style(Color::LightBlue)
-        .title("Login")
+        .title(title)
         .title_alignment(Alignment::Center);
 
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints(vec![Constraint::Length(1), Constraint::Length(1)])
+        .constraints(vec![
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
         .margin(1)
         .split(area);
 
-    let prompt = Paragraph::new(Text::raw("Enter your username"))
+    let prompt = Paragraph::new(Text::raw(prompt))
         .alignment(Alignment::Left)
         .style(Color::Blue);
 
     frame.render_widget(block.clone(), area);
 
-    let user_line = Paragraph::new(user_input)
+    let shown = if masked {
+        "\u{2022}".repeat(app.login_input.chars().count())
+    } else {
+        app.login_input.clone()
+    };
+    let user_line = Paragraph::new(shown)
         .style(Style::default().fg(Color::LightRed))
         .alignment(Alignment::Center);
     frame.render_widget(prompt, chunks[0]);
     frame.render_widget(user_line, chunks[1]);
+    if let Some(message) = &app.login_message {
+        let message = Paragraph::new(message.as_str())
+            .style(Style::default().fg(Color::Yellow))
+            .alignment(Alignment::Center);
+        frame.render_widget(message, chunks[2]);
+    }
 
     Ok(())
 }
 
 pub fn render_test_opts(frame: &mut ratatui::Frame, app: &TypeTui) -> AppResult<()> {
-    let popup_area = centered_rect(60, 50, frame.area());
+    let popup_area = centered_rect(80, 80, frame.area());
 
+    // test modes on the left, options that apply to them on the right
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(vec![Constraint::Percentage(55), Constraint::Percentage(45)])
+        .split(popup_area);
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints(vec![Constraint::Percentage(50), Constraint::Percentage(50)])
-        .split(popup_area);
+        .constraints(vec![
+            Constraint::Length(3),
+            Constraint::Length(app.test_opts.seconds_options.len() as u16 + 2),
+            Constraint::Length(QuoteLength::ALL.len() as u16 + 2),
+            Constraint::Min(3),
+        ])
+        .split(columns[0]);
     let words_border_style = if let TestOptsFocus::Words = app.test_opts.focus {
         Style::default().fg(Color::LightRed)
     } else {
@@ -464,6 +971,83 @@ pub fn render_test_opts(frame: &mut ratatui::Frame, app: &TypeTui) -> AppResult<
     list_state.select(Some(app.test_opts.seconds_selected));
     frame.render_stateful_widget(seconds_list, chunks[1], &mut list_state);
 
+    let quote_border_style = if let TestOptsFocus::Quote = app.test_opts.focus {
+        Style::default().fg(Color::LightRed)
+    } else {
+        Style::default()
+    };
+    let quote_block = Block::default()
+        .title("Quote")
+        .borders(Borders::ALL)
+        .border_style(quote_border_style);
+    let quote_options: Vec<ListItem> = QuoteLength::ALL
+        .iter()
+        .map(|length| {
+            ListItem::new(format!(
+                "{} ({})",
+                length.as_str(),
+                app.test_opts.quotes.count(*length)
+            ))
+        })
+        .collect();
+    let quote_list = List::new(quote_options)
+        .block(quote_block)
+        .highlight_style(Style::default().fg(Color::LightRed))
+        .highlight_symbol("-> ");
+
+    let mut quote_state = ratatui::widgets::ListState::default();
+    quote_state.select(Some(app.test_opts.quote_selected));
+    frame.render_stateful_widget(quote_list, chunks[2], &mut quote_state);
+
+    let code_border_style = if let TestOptsFocus::Code = app.test_opts.focus {
+        Style::default().fg(Color::LightRed)
+    } else {
+        Style::default()
+    };
+    let code_block = Block::default()
+        .title("Code")
+        .borders(Borders::ALL)
+        .border_style(code_border_style);
+    let code_options: Vec<ListItem> = app
+        .test_opts
+        .snippets
+        .languages()
+        .iter()
+        .map(|(language, count)| ListItem::new(format!("{} ({})", language.as_str(), count)))
+        .collect();
+    let code_list = List::new(code_options)
+        .block(code_block)
+        .highlight_style(Style::default().fg(Color::LightRed))
+        .highlight_symbol("-> ");
+
+    let mut code_state = ratatui::widgets::ListState::default();
+    code_state.select(Some(app.test_opts.code_selected));
+    frame.render_stateful_widget(code_list, chunks[3], &mut code_state);
+
+    let options_border_style = if let TestOptsFocus::Options = app.test_opts.focus {
+        Style::default().fg(Color::LightRed)
+    } else {
+        Style::default()
+    };
+    let options_block = Block::default()
+        .title("Options \u{2190}/\u{2192} to change")
+        .borders(Borders::ALL)
+        .border_style(options_border_style);
+    let options: Vec<ListItem> = TestOption::ALL
+        .iter()
+        .map(|option| ListItem::new(app.test_opts.option_label(*option)))
+        .collect();
+    let options_list = List::new(options)
+        .block(options_block)
+        .highlight_style(Style::default().fg(Color::LightRed))
+        .highlight_symbol("-> ");
+
+    let mut options_state = ratatui::widgets::ListState::default();
+    if let TestOptsFocus::Options = app.test_opts.focus {
+        options_state.select(Some(app.test_opts.options_selected));
+    }
+    frame.render_stateful_widget(options_list, columns[1], &mut options_state);
+
     Ok(())
 }
 
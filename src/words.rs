@@ -0,0 +1,268 @@
+use std::{
+    fs,
+    path::Path,
+    sync::{Arc, LazyLock},
+};
+
+use rand::{seq::IndexedRandom, Rng};
+
+use crate::paths;
+
+/// The default list, ordered by frequency. Compiled in so the binary works from any directory.
+const BUILTIN_WORDS: &str = include_str!("../20k.txt");
+const BUILTIN_NAME: &str = "english 20k";
+
+static BUILTIN: LazyLock<Arc<WordList>> =
+    LazyLock::new(|| Arc::new(WordList::parse(BUILTIN_NAME, BUILTIN_WORDS)));
+
+/// How far down a frequency ordered list words are drawn from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WordBand {
+    Top200,
+    Top1k,
+    Top5k,
+    Top10k,
+    #[default]
+    Full,
+}
+
+impl WordBand {
+    pub const ALL: [WordBand; 5] = [
+        WordBand::Top200,
+        WordBand::Top1k,
+        WordBand::Top5k,
+        WordBand::Top10k,
+        WordBand::Full,
+    ];
+
+    /// Number of leading words to sample from, `None` for the whole list.
+    pub fn limit(&self) -> Option<usize> {
+        match self {
+            WordBand::Top200 => Some(200),
+            WordBand::Top1k => Some(1_000),
+            WordBand::Top5k => Some(5_000),
+            WordBand::Top10k => Some(10_000),
+            WordBand::Full => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WordBand::Top200 => "top 200",
+            WordBand::Top1k => "top 1k",
+            WordBand::Top5k => "top 5k",
+            WordBand::Top10k => "top 10k",
+            WordBand::Full => "full",
+        }
+    }
+}
+
+/// A parsed word list, one word per line in the source file.
+#[derive(Debug, PartialEq, Eq)]
+pub struct WordList {
+    pub name: String,
+    words: Vec<String>,
+}
+
+impl WordList {
+    pub fn builtin() -> Arc<WordList> {
+        Arc::clone(&BUILTIN)
+    }
+
+    fn parse(name: &str, contents: &str) -> WordList {
+        WordList {
+            name: name.to_string(),
+            words: contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+
+    fn load(path: &Path) -> Option<WordList> {
+        let name = path.file_stem()?.to_string_lossy();
+        let contents = fs::read_to_string(path).ok()?;
+        let list = WordList::parse(&name, &contents);
+        (!list.words.is_empty()).then_some(list)
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Picks `num_words` words from the part of the list covered by `band`. Words only repeat
+    /// once every word in the band has been used.
+    pub fn sample<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        num_words: usize,
+        band: WordBand,
+    ) -> Vec<&str> {
+        let pool = match band.limit() {
+            Some(limit) => &self.words[..limit.min(self.words.len())],
+            None => &self.words[..],
+        };
+        let mut words = Vec::with_capacity(num_words);
+        while words.len() < num_words && !pool.is_empty() {
+            let wanted = (num_words - words.len()).min(pool.len());
+            words.extend(pool.choose_multiple(rng, wanted).map(String::as_str));
+        }
+        words
+    }
+}
+
+/// Turns sampled words into test tokens, mixing in numbers and punctuation when enabled.
+/// `sentence_start` says whether the first word begins a sentence, so text appended to a
+/// running test carries on from where it left off.
+pub fn dress_words<R: Rng + ?Sized>(
+    words: &[&str],
+    rng: &mut R,
+    punctuation: bool,
+    numbers: bool,
+    mut sentence_start: bool,
+) -> Vec<String> {
+    words
+        .iter()
+        .map(|word| {
+            let mut token = if numbers && rng.random_bool(0.15) {
+                let digits = rng.random_range(1..=4);
+                rng.random_range(10u32.pow(digits - 1)..10u32.pow(digits))
+                    .to_string()
+            } else {
+                word.to_string()
+            };
+            if !punctuation {
+                return token;
+            }
+
+            if sentence_start {
+                token = capitalize(&token);
+            }
+            let wrap: f64 = rng.random();
+            if wrap < 0.02 {
+                token = format!("\"{}\"", token);
+            } else if wrap < 0.04 {
+                token = format!("({})", token);
+            }
+            let mark: f64 = rng.random();
+            sentence_start = mark < 0.11;
+            if mark < 0.08 {
+                token.push('.');
+            } else if mark < 0.10 {
+                token.push('?');
+            } else if mark < 0.11 {
+                token.push('!');
+            } else if mark < 0.21 {
+                token.push(',');
+            } else if mark < 0.22 {
+                token.push(';');
+            } else if mark < 0.23 {
+                token.push(':');
+            }
+            token
+        })
+        .collect()
+}
+
+/// Whether the next word of `text` should start a new sentence.
+pub fn ends_sentence(text: &str) -> bool {
+    text.trim_end()
+        .trim_end_matches(['"', ')'])
+        .chars()
+        .last()
+        .is_none_or(|c| matches!(c, '.' | '?' | '!'))
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Every word list available to this session, read and parsed once at startup so tests never
+/// touch the disk while the user is typing.
+#[derive(Clone, Debug)]
+pub struct WordSource {
+    lists: Vec<Arc<WordList>>,
+}
+
+impl Default for WordSource {
+    fn default() -> Self {
+        Self {
+            lists: vec![WordList::builtin()],
+        }
+    }
+}
+
+impl WordSource {
+    /// The builtin list followed by every readable `*.txt` in `<config dir>/words`, sorted by
+    /// name.
+    pub fn load() -> WordSource {
+        let mut found: Vec<WordList> = fs::read_dir(paths::config_dir().join("words"))
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "txt"))
+            .filter_map(|path| WordList::load(&path))
+            .collect();
+        found.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut source = WordSource::default();
+        source.lists.extend(found.into_iter().map(Arc::new));
+        source
+    }
+
+    pub fn len(&self) -> usize {
+        self.lists.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lists.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> &Arc<WordList> {
+        &self.lists[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn sample_keeps_the_count_within_the_band() {
+        let contents: Vec<String> = (0..300).map(|i| format!("w{}", i)).collect();
+        let list = WordList::parse("test", &contents.join("\n"));
+        let top200 = &list.words[..200];
+        let cases = [
+            // (words, band)
+            (50, WordBand::Top200),
+            (200, WordBand::Top200),
+            (500, WordBand::Top200),
+            (500, WordBand::Full),
+            (0, WordBand::Top200),
+        ];
+        for (num_words, band) in cases {
+            let mut rng = ChaCha8Rng::seed_from_u64(1);
+            let words = list.sample(&mut rng, num_words, band);
+            assert_eq!(words.len(), num_words, "{} {:?}", num_words, band);
+            if band == WordBand::Top200 {
+                assert!(words.iter().all(|w| top200.iter().any(|t| t == w)));
+            }
+        }
+        let empty = WordList::parse("empty", "");
+        assert!(empty
+            .sample(&mut ChaCha8Rng::seed_from_u64(1), 10, WordBand::Full)
+            .is_empty());
+    }
+}